@@ -0,0 +1,558 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{ext::IdentExt, parse_macro_input, Data, DeriveInput, Fields, Ident, LitStr, Type};
+
+/// Derive `sqlx_model::SqliteModel` along with a matching `sqlx::FromRow` implementation.
+///
+/// # Container attributes
+/// - `#[model(table = "users")]`: The name of the table. Defaults to the name of the struct.
+/// - `#[model(error = MyError)]`: The `SqliteModel::Error` type. Defaults to `sqlx_model::Error`.
+/// - `#[model(max_limit = 100)]`: The largest `limit` a `ModelQuery` of the model accepts, also
+///   applied when the query gives none. Unbounded by default.
+/// - `#[model(hooks)]`: Runs the lifecycle hooks of the type's implementation of
+///   `sqlx_model::ModelHooks`, which must be written by hand.
+///
+/// # Field attributes
+/// - `#[model(primary_key)]`: Marks the primary key column.
+/// - `#[model(skip_insert)]`: Leaves the column out of `insert` so the database default applies.
+/// - `#[model(rename = "...")]`: The name of the column in the database, if it differs from
+///   the name of the field.
+/// - `#[model(json)]`: Stores the field as JSON text and reads it back with `sqlx::types::Json`.
+///   Required for nested structs, maps and sequences other than `Vec<u8>`, which is otherwise
+///   stored as a blob. `Option<T>` fields map `NULL` to `None`.
+/// - `#[model(unique)]`: Declares the column `unique` in the generated `create table`.
+/// - `#[model(default = "...")]`: An SQL expression the database uses as the value of the
+///   column when none is inserted, eg `"unixepoch()"`.
+/// - `#[model(validate(...))]`: Validation rules checked before the model is written, any of
+///   `length(min = 1, max = 20)`, `range(min = 0, max = 150)`, `regex = "^[a-z]+$"`, `email`,
+///   `non_empty` and `custom = path::to::fn`, where the function has the signature
+///   `fn(&serde_json::Value) -> Result<(), String>`. Either bound of `length` and `range` may
+///   be left out. An invalid `regex` pattern is a compile error.
+/// - `#[model(created_at)]` and `#[model(updated_at)]`: Timestamp columns, skipped on insert so
+///   the database sets them to the current time. `updated_at` is also set to the current time by
+///   every `upsert` and `update`, which never write `created_at`. The format defaults to
+///   `iso8601` for `String` fields and `unix_seconds` otherwise, and can be given as eg
+///   `#[model(updated_at = "unix_millis")]`.
+/// - `#[model(deleted_at)]`: Soft deletes the model, making `delete` set the column to the
+///   current time instead of removing the record, and hiding such records from every `select_*`.
+///   The field must be an `Option<T>`, and takes a format like `created_at`.
+/// - `#[model(version)]`: An integer column counting the writes to the record, for optimistic
+///   locking. It is skipped on insert so it starts at `0`, and every `upsert` and `update`
+///   increments it, failing with `Error::Conflict` if the record no longer has the version of
+///   the model being written.
+/// - `#[model(affinity = "...")]`: The SQLite type of the column, one of `integer`, `real`,
+///   `text`, `blob` or `numeric`. Inferred from the type of the field for integers, floats,
+///   `bool`, `String` and `Vec<u8>`; other types are declared without a type unless given one.
+///
+/// Columns are `not null` unless the field is an `Option<T>`. Each field is known to
+/// `ModelQuery` and the router by the name it is serialized with, following its
+/// `#[serde(rename = "...")]` or the struct's `#[serde(rename_all = "...")]`.
+#[proc_macro_derive(SqliteModel, attributes(model))]
+pub fn derive_sqlite_model(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct ContainerAttrs {
+    table: Option<LitStr>,
+    error: Option<Type>,
+    max_limit: Option<syn::LitInt>,
+    hooks: bool,
+    rename_all: Option<LitStr>,
+}
+
+impl ContainerAttrs {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut attrs = ContainerAttrs {
+            table: None,
+            error: None,
+            max_limit: None,
+            hooks: false,
+            rename_all: serde_attr(&input.attrs, "rename_all")?,
+        };
+        for attr in input.attrs.iter().filter(|a| a.path().is_ident("model")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("table") {
+                    attrs.table = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("error") {
+                    attrs.error = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("max_limit") {
+                    attrs.max_limit = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("hooks") {
+                    attrs.hooks = true;
+                } else {
+                    return Err(meta.error("unsupported model attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(attrs)
+    }
+}
+
+struct FieldAttrs {
+    ident: Ident,
+    ty: Type,
+    /// The name serde gives the field
+    field: String,
+    column: String,
+    primary_key: bool,
+    skip_insert: bool,
+    version: bool,
+    json: bool,
+    unique: bool,
+    default: Option<LitStr>,
+    affinity: Option<Ident>,
+    timestamp: Option<(Ident, Option<Ident>)>,
+    rules: Vec<TokenStream2>,
+}
+
+impl FieldAttrs {
+    fn parse(field: &syn::Field, rename_all: Option<&LitStr>) -> syn::Result<Self> {
+        let ident = field
+            .ident
+            .clone()
+            .ok_or_else(|| syn::Error::new_spanned(field, "expected a named field"))?;
+        let serde_name = match (serde_attr(&field.attrs, "rename")?, rename_all) {
+            (Some(name), _) => name.value(),
+            (None, Some(rule)) => rename_field(&ident.unraw().to_string(), rule)?,
+            (None, None) => ident.unraw().to_string(),
+        };
+        let mut attrs = FieldAttrs {
+            field: serde_name,
+            column: ident.to_string(),
+            ident,
+            ty: field.ty.clone(),
+            primary_key: false,
+            skip_insert: false,
+            version: false,
+            json: false,
+            unique: false,
+            default: None,
+            affinity: None,
+            timestamp: None,
+            rules: Vec::new(),
+        };
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("model")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("primary_key") {
+                    attrs.primary_key = true;
+                } else if meta.path.is_ident("skip_insert") {
+                    attrs.skip_insert = true;
+                } else if meta.path.is_ident("version") {
+                    attrs.version = true;
+                } else if meta.path.is_ident("json") {
+                    attrs.json = true;
+                } else if meta.path.is_ident("unique") {
+                    attrs.unique = true;
+                } else if meta.path.is_ident("default") {
+                    attrs.default = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("affinity") {
+                    let name: LitStr = meta.value()?.parse()?;
+                    let variant = match name.value().to_lowercase().as_str() {
+                        "integer" => "Integer",
+                        "real" => "Real",
+                        "text" => "Text",
+                        "blob" => "Blob",
+                        "numeric" => "Numeric",
+                        _ => return Err(meta.error("unknown SQLite affinity")),
+                    };
+                    attrs.affinity = Some(Ident::new(variant, name.span()));
+                } else if ["created_at", "updated_at", "deleted_at"]
+                    .iter()
+                    .any(|kind| meta.path.is_ident(kind))
+                {
+                    let kind = meta.path.get_ident().cloned();
+                    let format = match meta.input.peek(syn::Token![=]) {
+                        true => {
+                            let name: LitStr = meta.value()?.parse()?;
+                            let variant = match name.value().to_lowercase().as_str() {
+                                "unix_seconds" => "UnixSeconds",
+                                "unix_millis" => "UnixMillis",
+                                "iso8601" => "Iso8601",
+                                _ => return Err(meta.error("unknown timestamp format")),
+                            };
+                            Some(Ident::new(variant, name.span()))
+                        }
+                        false => None,
+                    };
+                    attrs.timestamp = kind.map(|kind| (kind, format));
+                } else if meta.path.is_ident("validate") {
+                    meta.parse_nested_meta(|rule| {
+                        attrs.rules.push(parse_rule(&rule)?);
+                        Ok(())
+                    })?;
+                } else if meta.path.is_ident("rename") {
+                    let name: LitStr = meta.value()?.parse()?;
+                    attrs.column = name.value();
+                } else {
+                    return Err(meta.error("unsupported model attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        let soft_delete = matches!(&attrs.timestamp, Some((kind, _)) if kind == "deleted_at");
+        if soft_delete && option_inner(&attrs.ty).is_none() {
+            return Err(syn::Error::new_spanned(
+                &attrs.ty,
+                "a deleted_at field must be an Option",
+            ));
+        }
+        Ok(attrs)
+    }
+
+    /// The `sqlx_model::Column` constant describing this field
+    fn column_def(&self) -> TokenStream2 {
+        let (name, field) = (&self.column, &self.field);
+        let mut def = quote! { ::sqlx_model::Column::new(#name).field(#field) };
+        if self.primary_key {
+            def = quote! { #def.primary_key() };
+        }
+        if self.skip_insert {
+            def = quote! { #def.skip_insert() };
+        }
+        if self.json {
+            def = quote! { #def.json() };
+        }
+        let inner = option_inner(&self.ty);
+        let affinity = match (&self.affinity, self.json) {
+            (Some(affinity), _) => Some(affinity.clone()),
+            (None, true) => Some(Ident::new("Text", proc_macro2::Span::call_site())),
+            (None, false) => type_affinity(inner.unwrap_or(&self.ty)),
+        };
+        if let Some(affinity) = &affinity {
+            def = quote! { #def.affinity(::sqlx_model::Affinity::#affinity) };
+        }
+        if !self.json
+            && matches!(inner.unwrap_or(&self.ty), Type::Path(p) if p.path.is_ident("bool"))
+        {
+            def = quote! { #def.boolean() };
+        }
+        if inner.is_none() {
+            def = quote! { #def.not_null() };
+        }
+        if self.unique {
+            def = quote! { #def.unique() };
+        }
+        if self.version {
+            def = quote! { #def.version() };
+        }
+        if let Some((kind, format)) = &self.timestamp {
+            let format = format.clone().unwrap_or_else(|| {
+                let text = matches!(&affinity, Some(a) if a == "Text");
+                let variant = if text { "Iso8601" } else { "UnixSeconds" };
+                Ident::new(variant, kind.span())
+            });
+            def = quote! { #def.#kind(::sqlx_model::TimeFormat::#format) };
+        }
+        if let Some(default) = &self.default {
+            def = quote! { #def.default_expr(#default) };
+        }
+        def
+    }
+
+    /// The `sqlx_model::FieldRule` constants of the rules declared on this field
+    fn rule_defs(&self) -> impl Iterator<Item = TokenStream2> + '_ {
+        let column = &self.column;
+        self.rules
+            .iter()
+            .map(move |rule| quote! { ::sqlx_model::FieldRule::new(#column, #rule) })
+    }
+
+    /// The expression reading this field from `row` in the generated `FromRow` implementation
+    fn row_value(&self) -> TokenStream2 {
+        let column = &self.column;
+        if !self.json {
+            return quote! { row.try_get(#column)? };
+        }
+        let json = quote! { ::sqlx_model::__private::sqlx::types::Json };
+        match option_inner(&self.ty) {
+            Some(inner) => quote! {
+                row.try_get::<Option<#json<#inner>>, _>(#column)?.map(|j| j.0)
+            },
+            None => {
+                let ty = &self.ty;
+                quote! { row.try_get::<#json<#ty>, _>(#column)?.0 }
+            }
+        }
+    }
+}
+
+/// Parse one rule of `#[model(validate(...))]` into a `sqlx_model::Rule`
+fn parse_rule(rule: &syn::meta::ParseNestedMeta) -> syn::Result<TokenStream2> {
+    let path = quote! { ::sqlx_model::Rule };
+    if rule.path.is_ident("email") {
+        Ok(quote! { #path::Email })
+    } else if rule.path.is_ident("non_empty") {
+        Ok(quote! { #path::NonEmpty })
+    } else if rule.path.is_ident("regex") {
+        let pattern: LitStr = rule.value()?.parse()?;
+        if let Err(e) = regex::Regex::new(&pattern.value()) {
+            return Err(syn::Error::new(pattern.span(), e));
+        }
+        Ok(quote! { #path::Regex(#pattern) })
+    } else if rule.path.is_ident("custom") {
+        let check: syn::Path = rule.value()?.parse()?;
+        Ok(quote! { #path::Custom(#check) })
+    } else if rule.path.is_ident("length") || rule.path.is_ident("range") {
+        let mut min = quote! { None };
+        let mut max = quote! { None };
+        let is_range = rule.path.is_ident("range");
+        rule.parse_nested_meta(|bound| {
+            let val: syn::Expr = bound.value()?.parse()?;
+            let val = match is_range {
+                true => quote! { Some((#val) as f64) },
+                false => quote! { Some(#val) },
+            };
+            if bound.path.is_ident("min") {
+                min = val;
+            } else if bound.path.is_ident("max") {
+                max = val;
+            } else {
+                return Err(bound.error("expected min or max"));
+            }
+            Ok(())
+        })?;
+        match is_range {
+            true => Ok(quote! { #path::Range { min: #min, max: #max } }),
+            false => Ok(quote! { #path::Length { min: #min, max: #max } }),
+        }
+    } else {
+        Err(rule.error("unsupported validation rule"))
+    }
+}
+
+/// The `hooks` of the generated `SqliteModel` implementation, running those of `ModelHooks`
+fn hook_fns() -> TokenStream2 {
+    quote! {
+        fn hooks() -> Option<::sqlx_model::Hooks<Self>> {
+            Some(::sqlx_model::Hooks::new())
+        }
+    }
+}
+
+/// The value of `key` in the `#[serde(...)]` attributes `attrs`, taking the `serialize` name
+/// when it is given as `key(serialize = "...", deserialize = "...")`. Every other serde
+/// attribute is skipped
+fn serde_attr(attrs: &[syn::Attribute], key: &str) -> syn::Result<Option<LitStr>> {
+    let mut value = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(key) && meta.input.peek(syn::Token![=]) {
+                value = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident(key) {
+                meta.parse_nested_meta(|name| {
+                    let lit: LitStr = name.value()?.parse()?;
+                    if name.path.is_ident("serialize") {
+                        value = Some(lit);
+                    }
+                    Ok(())
+                })?;
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                let _nested;
+                syn::parenthesized!(_nested in meta.input);
+            }
+            Ok(())
+        })?;
+    }
+    Ok(value)
+}
+
+/// The name serde gives the snake case field `name` under `#[serde(rename_all = "...")]`
+fn rename_field(name: &str, rule: &LitStr) -> syn::Result<String> {
+    let pascal: String = name
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    let renamed = match rule.value().as_str() {
+        "lowercase" | "snake_case" => name.to_string(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => name.to_uppercase(),
+        "PascalCase" => pascal,
+        "camelCase" => {
+            let mut chars = pascal.chars();
+            match chars.next() {
+                Some(first) => first.to_lowercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+        "kebab-case" => name.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => name.replace('_', "-").to_uppercase(),
+        _ => {
+            return Err(syn::Error::new(
+                rule.span(),
+                "unknown serde rename_all rule",
+            ))
+        }
+    };
+    Ok(renamed)
+}
+
+/// The `T` of an `Option<T>` type, matched by name since the type is only known syntactically
+fn option_inner(ty: &Type) -> Option<&Type> {
+    type_arg(ty, "Option")
+}
+
+/// The `T` of a `name<T>` type
+fn type_arg<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != name {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            syn::GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The `sqlx_model::Affinity` variant of a column holding `ty`, for the types whose affinity can
+/// be told from their name
+fn type_affinity(ty: &Type) -> Option<Ident> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    let variant = match segment.ident.to_string().as_str() {
+        "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64" | "usize"
+        | "bool" => "Integer",
+        "f32" | "f64" => "Real",
+        "String" | "char" => "Text",
+        "Vec" => match type_arg(ty, "Vec")? {
+            Type::Path(arg) if arg.path.is_ident("u8") => "Blob",
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(Ident::new(variant, segment.ident.span()))
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "SqliteModel cannot be derived for generic types",
+        ));
+    }
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "SqliteModel can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "SqliteModel can only be derived for structs",
+            ))
+        }
+    };
+
+    let container = ContainerAttrs::parse(&input)?;
+    let fields = named
+        .iter()
+        .map(|f| FieldAttrs::parse(f, container.rename_all.as_ref()))
+        .collect::<syn::Result<Vec<_>>>()?;
+    let error = container
+        .error
+        .unwrap_or_else(|| syn::parse_quote!(::sqlx_model::Error));
+    let table = container
+        .table
+        .map(|t| t.value())
+        .unwrap_or_else(|| ident.to_string());
+
+    let column_defs = fields.iter().map(FieldAttrs::column_def);
+    let column_values = fields.iter().map(|f| {
+        let (field, column) = (&f.ident, &f.column);
+        quote! { (#column.to_string(), ::sqlx_model::__private::serde_json::to_value(&self.#field)?) }
+    });
+    let rule_defs: Vec<TokenStream2> = fields.iter().flat_map(FieldAttrs::rule_defs).collect();
+    let rules = match rule_defs.is_empty() {
+        true => TokenStream2::new(),
+        false => quote! {
+            fn rules() -> &'static [::sqlx_model::FieldRule] {
+                const RULES: &[::sqlx_model::FieldRule] = &[#(#rule_defs),*];
+                RULES
+            }
+        },
+    };
+    let max_limit = match container.max_limit {
+        Some(max) => quote! {
+            fn max_limit() -> Option<u64> {
+                Some(#max)
+            }
+        },
+        None => TokenStream2::new(),
+    };
+    let hooks = match container.hooks {
+        true => hook_fns(),
+        false => TokenStream2::new(),
+    };
+    let row_fields = fields.iter().map(|f| {
+        let (field, value) = (&f.ident, f.row_value());
+        quote! { #field: #value }
+    });
+
+    Ok(quote! {
+        impl ::sqlx_model::SqliteModel for #ident {
+            type Error = #error;
+
+            fn table_name() -> String {
+                #table.to_string()
+            }
+
+            fn columns() -> &'static [::sqlx_model::Column] {
+                const COLUMNS: &[::sqlx_model::Column] = &[#(#column_defs),*];
+                COLUMNS
+            }
+
+            fn column_values(
+                &self,
+            ) -> Result<
+                Vec<(String, ::sqlx_model::__private::serde_json::Value)>,
+                ::sqlx_model::__private::serde_json::Error,
+            > {
+                Ok(vec![#(#column_values),*])
+            }
+
+            #rules
+
+            #max_limit
+
+            #hooks
+        }
+
+        impl<'r> ::sqlx_model::__private::sqlx::FromRow<'r, ::sqlx_model::__private::sqlx::sqlite::SqliteRow>
+            for #ident
+        {
+            fn from_row(
+                row: &'r ::sqlx_model::__private::sqlx::sqlite::SqliteRow,
+            ) -> Result<Self, ::sqlx_model::__private::sqlx::Error> {
+                use ::sqlx_model::__private::sqlx::Row;
+                Ok(Self {
+                    #(#row_fields),*
+                })
+            }
+        }
+    })
+}
@@ -0,0 +1,74 @@
+use sqlx::{sqlite::SqliteRow, FromRow, Row, TypeInfo, ValueRef};
+
+use crate::BasicType;
+
+/// An SQL aggregate function applied to a column by `SqliteModel::aggregate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    pub(crate) fn as_sql(&self) -> &'static str {
+        match self {
+            Aggregate::Sum => "sum",
+            Aggregate::Avg => "avg",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+        }
+    }
+}
+
+/// A row of values read back as whatever type SQLite stored them as, for queries whose result
+/// columns are not a model, eg aggregates
+pub(crate) struct BasicRow(pub Vec<BasicType>);
+
+impl<'r> FromRow<'r, SqliteRow> for BasicRow {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        let mut vals = Vec::with_capacity(row.len());
+        for i in 0..row.len() {
+            let raw = row.try_get_raw(i)?;
+            if raw.is_null() {
+                vals.push(BasicType::Null);
+                continue;
+            }
+            // The type info of a value, as opposed to a column, is the type it is stored as
+            let val = match raw.type_info().name() {
+                "INTEGER" => BasicType::Integer(row.try_get(i)?),
+                "REAL" => BasicType::Real(row.try_get(i)?),
+                "BLOB" => BasicType::Blob(row.try_get(i)?),
+                _ => BasicType::Text(row.try_get(i)?),
+            };
+            vals.push(val);
+        }
+        Ok(BasicRow(vals))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BasicRow;
+    use crate::BasicType;
+
+    #[tokio::test]
+    async fn test_basic_row() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        let row: BasicRow = sqlx::query_as("select 1, 2.5, 'a', x'0102', null")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            row.0,
+            vec![
+                BasicType::Integer(1),
+                BasicType::Real(2.5),
+                BasicType::Text("a".to_string()),
+                BasicType::Blob(vec![1, 2]),
+                BasicType::Null,
+            ]
+        );
+    }
+}
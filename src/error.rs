@@ -0,0 +1,467 @@
+use std::fmt::Display;
+
+use crate::FieldError;
+
+/// The kind of SQLite constraint a write violated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintKind {
+    /// A `unique` or `primary key` constraint
+    Unique,
+    ForeignKey,
+    NotNull,
+    Check,
+}
+
+impl Display for ConstraintKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstraintKind::Unique => write!(f, "unique"),
+            ConstraintKind::ForeignKey => write!(f, "foreign key"),
+            ConstraintKind::NotNull => write!(f, "not null"),
+            ConstraintKind::Check => write!(f, "check"),
+        }
+    }
+}
+
+/// The errors returned by this crate. It implements every conversion `SqliteModel::Error`
+/// requires, so it can be used directly as the error type of a model. With the `axum` feature
+/// it also implements `IntoResponse`, so handlers can return it directly
+#[derive(Debug)]
+pub enum Error {
+    /// No record of `table` matched the value of `column`
+    NotFound {
+        table: String,
+        column: String,
+    },
+    /// A query which must return a record returned none, where the table and column it looked
+    /// for are not known, eg `sqlx::Error::RowNotFound` from a `fetch_one`
+    RowNotFound,
+    /// A write to a record of `table` expected a value of the version column `column` which the
+    /// record no longer has, because it was written since it was read
+    Conflict {
+        table: String,
+        column: String,
+    },
+    /// `value` cannot be stored in SQLite, so it cannot be bound as the value of `column`
+    UnsupportedValue {
+        column: String,
+        value: serde_json::Value,
+    },
+    /// A table or column name is not a plain SQL identifier
+    InvalidIdentifier(String),
+    /// A column name is not one of the columns declared by the model
+    UnknownColumn(String),
+    /// A pagination cursor could not be decoded
+    InvalidCursor(String),
+    /// The arguments of a query cannot produce a valid statement, eg an update with no columns
+    InvalidQuery(String),
+    /// A parameter of a request, eg a query string filter, names an unknown field or holds a
+    /// value which cannot be converted to the type of its column
+    InvalidParameter(String),
+    /// A model broke one or more of its validation rules, so it was not written
+    Validation(Vec<FieldError>),
+    /// A write was rejected by a constraint on the table. `constraint` holds whatever SQLite
+    /// reports as the failing constraint, eg `users.email` for a unique column
+    ConstraintViolation {
+        kind: ConstraintKind,
+        constraint: String,
+    },
+    /// A set of migrations is malformed, eg two migrations share a version, or a migration
+    /// cannot be applied or rolled back
+    Migration(String),
+    /// The script of an applied migration no longer matches the checksum recorded when it ran
+    ChecksumMismatch {
+        version: i64,
+        name: String,
+    },
+    Serialization(serde_json::Error),
+    Database(sqlx::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NotFound { table, column } => {
+                write!(f, "no record of {} matches the given {}", table, column)
+            }
+            Error::RowNotFound => write!(f, "no record was found"),
+            Error::Conflict { table, column } => write!(
+                f,
+                "the record of {} was written since its {} was read",
+                table, column
+            ),
+            Error::UnsupportedValue { column, value } => {
+                write!(
+                    f,
+                    "cannot store {} in column {} as a Sqlite type",
+                    value, column
+                )
+            }
+            Error::InvalidIdentifier(name) => write!(f, "{:?} is not a valid SQL identifier", name),
+            Error::UnknownColumn(name) => write!(f, "{:?} is not a known column", name),
+            Error::InvalidCursor(msg) => write!(f, "invalid cursor: {}", msg),
+            Error::InvalidQuery(msg) => write!(f, "invalid query: {}", msg),
+            Error::InvalidParameter(msg) => write!(f, "invalid parameter: {}", msg),
+            Error::Validation(errors) => {
+                let fields: Vec<String> = errors
+                    .iter()
+                    .map(|e| format!("{} {}", e.field, e.message))
+                    .collect();
+                write!(f, "validation failed: {}", fields.join(", "))
+            }
+            Error::ConstraintViolation { kind, constraint } => {
+                write!(f, "{} constraint failed: {}", kind, constraint)
+            }
+            Error::Migration(msg) => write!(f, "migration error: {}", msg),
+            Error::ChecksumMismatch { version, name } => write!(
+                f,
+                "migration {} ({}) has changed since it was applied",
+                version, name
+            ),
+            Error::Serialization(e) => write!(f, "serialization error: {}", e),
+            Error::Database(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Serialization(e) => Some(e),
+            Error::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(value: sqlx::Error) -> Self {
+        let db_err = match &value {
+            sqlx::Error::RowNotFound => return Error::RowNotFound,
+            sqlx::Error::Database(db_err) => db_err,
+            _ => return Error::Database(value),
+        };
+        let kind = match db_err.kind() {
+            sqlx::error::ErrorKind::UniqueViolation => ConstraintKind::Unique,
+            sqlx::error::ErrorKind::ForeignKeyViolation => ConstraintKind::ForeignKey,
+            sqlx::error::ErrorKind::NotNullViolation => ConstraintKind::NotNull,
+            sqlx::error::ErrorKind::CheckViolation => ConstraintKind::Check,
+            _ => return Error::Database(value),
+        };
+        // SQLite reports the constraint in the message, eg
+        // "UNIQUE constraint failed: users.email", rather than through `constraint()`
+        let constraint = match db_err.constraint() {
+            Some(c) => c.to_string(),
+            None => db_err
+                .message()
+                .split_once("constraint failed: ")
+                .map(|(_, c)| c.to_string())
+                .unwrap_or_default(),
+        };
+        Error::ConstraintViolation { kind, constraint }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Error::Serialization(value)
+    }
+}
+
+#[cfg(feature = "axum")]
+impl Error {
+    /// The HTTP status a handler returning this error responds with
+    ///
+    /// Errors caused by the request are client errors. The router and extractors report
+    /// unknown fields and malformed values taken from the request as `InvalidParameter`, so
+    /// `UnknownColumn`, `InvalidIdentifier`, `InvalidQuery` and `Serialization` only arise from
+    /// the code of the application, and are server errors
+    pub fn status(&self) -> axum::http::StatusCode {
+        use axum::http::StatusCode;
+
+        match self {
+            Error::InvalidParameter(_) | Error::InvalidCursor(_) => StatusCode::BAD_REQUEST,
+            Error::NotFound { .. } | Error::RowNotFound => StatusCode::NOT_FOUND,
+            Error::Conflict { .. }
+            | Error::ConstraintViolation {
+                kind: ConstraintKind::Unique | ConstraintKind::ForeignKey,
+                ..
+            } => StatusCode::CONFLICT,
+            Error::UnsupportedValue { .. }
+            | Error::Validation(_)
+            | Error::ConstraintViolation {
+                kind: ConstraintKind::NotNull | ConstraintKind::Check,
+                ..
+            } => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::InvalidIdentifier(_)
+            | Error::UnknownColumn(_)
+            | Error::InvalidQuery(_)
+            | Error::Migration(_)
+            | Error::ChecksumMismatch { .. }
+            | Error::Serialization(_)
+            | Error::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Responds with an RFC 7807 problem details body. Only errors caused by the request describe
+/// themselves in `detail`; server errors never expose the statement or database message.
+/// Validation errors list every broken rule in an additional `errors` member
+#[cfg(feature = "axum")]
+impl axum::response::IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        let status = self.status();
+        let mut problem = serde_json::json!({
+            "type": "about:blank",
+            "title": status.canonical_reason().unwrap_or_default(),
+            "status": status.as_u16(),
+        });
+        let detail = match &self {
+            Error::ConstraintViolation { kind, .. } => Some(format!("{} constraint failed", kind)),
+            _ if status.is_client_error() => Some(self.to_string()),
+            _ => None,
+        };
+        if let Some(detail) = detail {
+            problem["detail"] = detail.into();
+        }
+        if let Error::Validation(errors) = &self {
+            problem["errors"] = serde_json::json!(errors);
+        }
+        let content_type = [(axum::http::header::CONTENT_TYPE, "application/problem+json")];
+        (status, content_type, problem.to_string()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConstraintKind, Error};
+    #[cfg(feature = "axum")]
+    use crate::FieldError;
+
+    #[tokio::test]
+    async fn test_from_constraint_violation() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::query(
+            r"create table parent (id integer primary key);
+            create table child (
+                id integer primary key,
+                name text not null unique check (length(name) > 1),
+                parent_id integer references parent(id)
+            );
+            pragma foreign_keys = on;",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("insert into child (name) values ('ab')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let cases = [
+            (
+                "insert into child (name) values ('ab')",
+                ConstraintKind::Unique,
+                "child.name",
+            ),
+            (
+                "insert into child (id, name) values (1, 'cd')",
+                ConstraintKind::Unique,
+                "child.id",
+            ),
+            (
+                "insert into child (name) values (null)",
+                ConstraintKind::NotNull,
+                "child.name",
+            ),
+            (
+                "insert into child (name) values ('a')",
+                ConstraintKind::Check,
+                "",
+            ),
+            (
+                "insert into child (name, parent_id) values ('ef', 4)",
+                ConstraintKind::ForeignKey,
+                "",
+            ),
+        ];
+        for (query, expected_kind, expected_constraint) in cases {
+            let err = sqlx::query(query).execute(&pool).await.unwrap_err();
+            match Error::from(err) {
+                Error::ConstraintViolation { kind, constraint } => {
+                    assert_eq!(kind, expected_kind, "{}", query);
+                    if !expected_constraint.is_empty() {
+                        assert_eq!(constraint, expected_constraint, "{}", query);
+                    }
+                }
+                e => panic!("{}: expected a constraint violation, found {:?}", query, e),
+            }
+        }
+
+        let err = sqlx::query("select * from missing")
+            .execute(&pool)
+            .await
+            .unwrap_err();
+        assert!(matches!(Error::from(err), Error::Database(_)));
+        assert!(matches!(
+            Error::from(sqlx::Error::RowNotFound),
+            Error::RowNotFound
+        ));
+    }
+
+    #[cfg(feature = "axum")]
+    #[test]
+    fn test_status() {
+        use axum::http::StatusCode;
+
+        let constraint = |kind| Error::ConstraintViolation {
+            kind,
+            constraint: String::new(),
+        };
+        let cases = [
+            (Error::InvalidParameter(String::new()), 400),
+            (Error::InvalidCursor(String::new()), 400),
+            (
+                Error::NotFound {
+                    table: "users".to_string(),
+                    column: "id".to_string(),
+                },
+                404,
+            ),
+            (
+                Error::Conflict {
+                    table: "users".to_string(),
+                    column: "version".to_string(),
+                },
+                409,
+            ),
+            (constraint(ConstraintKind::Unique), 409),
+            (constraint(ConstraintKind::ForeignKey), 409),
+            (constraint(ConstraintKind::NotNull), 422),
+            (constraint(ConstraintKind::Check), 422),
+            (
+                Error::UnsupportedValue {
+                    column: "id".to_string(),
+                    value: serde_json::json!(u64::MAX),
+                },
+                422,
+            ),
+            (Error::Validation(Vec::new()), 422),
+            (Error::InvalidIdentifier(String::new()), 500),
+            (Error::UnknownColumn(String::new()), 500),
+            (Error::InvalidQuery(String::new()), 500),
+            (Error::Migration(String::new()), 500),
+            (
+                Error::ChecksumMismatch {
+                    version: 1,
+                    name: "create_users".to_string(),
+                },
+                500,
+            ),
+            (
+                Error::Serialization(serde_json::from_str::<i64>("x").unwrap_err()),
+                500,
+            ),
+            (Error::from(sqlx::Error::RowNotFound), 404),
+            (Error::Database(sqlx::Error::PoolTimedOut), 500),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(
+                err.status(),
+                StatusCode::from_u16(expected).unwrap(),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[cfg(feature = "axum")]
+    #[tokio::test]
+    async fn test_into_response() {
+        use axum::{http::StatusCode, response::IntoResponse};
+        use http_body_util::BodyExt;
+
+        let cases = [
+            (
+                Error::NotFound {
+                    table: "users".to_string(),
+                    column: "id".to_string(),
+                },
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                Error::Conflict {
+                    table: "users".to_string(),
+                    column: "version".to_string(),
+                },
+                StatusCode::CONFLICT,
+            ),
+            (
+                Error::ConstraintViolation {
+                    kind: ConstraintKind::Unique,
+                    constraint: "users.email".to_string(),
+                },
+                StatusCode::CONFLICT,
+            ),
+            (
+                Error::ConstraintViolation {
+                    kind: ConstraintKind::ForeignKey,
+                    constraint: String::new(),
+                },
+                StatusCode::CONFLICT,
+            ),
+            (
+                Error::UnsupportedValue {
+                    column: "id".to_string(),
+                    value: serde_json::json!(u64::MAX),
+                },
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (
+                Error::Validation(vec![FieldError {
+                    field: "email".to_string(),
+                    code: "email",
+                    message: "must be an email address".to_string(),
+                }]),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (
+                Error::InvalidParameter("\"age\" is not a field of users".to_string()),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Error::InvalidQuery("select * from users".to_string()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+        for (err, expected_status) in cases {
+            let response = err.into_response();
+            assert_eq!(response.status(), expected_status);
+            assert_eq!(
+                response.headers()["content-type"],
+                "application/problem+json"
+            );
+            let bytes = response.into_body().collect().await.unwrap().to_bytes();
+            let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(body["status"], expected_status.as_u16());
+            assert_eq!(body["title"], expected_status.canonical_reason().unwrap());
+            assert!(!body.to_string().contains("select"));
+            assert!(!body.to_string().contains("users.email"));
+            if expected_status == StatusCode::UNPROCESSABLE_ENTITY && body["errors"].is_array() {
+                assert_eq!(body["errors"][0]["field"], "email");
+                assert_eq!(body["errors"][0]["code"], "email");
+            }
+        }
+
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        let err = sqlx::query("select * from missing")
+            .execute(&pool)
+            .await
+            .unwrap_err();
+        let response = Error::from(err).into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body.get("detail"), None);
+    }
+}
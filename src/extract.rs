@@ -0,0 +1,487 @@
+use std::marker::PhantomData;
+
+use axum::{
+    extract::{FromRef, FromRequestParts, Path, Query},
+    http::request::Parts,
+    response::IntoResponse,
+};
+use serde_json::Value;
+use sqlx::{sqlite::SqliteRow, FromRow, SqlitePool};
+
+use crate::{
+    ident::quote_ident,
+    router::{key_column, key_value},
+    Affinity, Condition, Error, Filter, Operator, Order, SelectOptions, SqliteModel,
+};
+
+/// A filtered, ordered and paged `select` of `M`, extracted from the query string of a request.
+///
+/// Every parameter other than the reserved `sort`, `limit` and `offset` filters on the field
+/// it names. A bare `field=value` matches equal values, while `field[op]=value` applies one of
+/// `eq`, `ne`, `lt`, `lte`, `gt`, `gte`, `like`, `in` (comma separated values) or `null`
+/// (`true` or `false`). `sort` is a comma separated list of fields, each descending if prefixed
+/// with `-`. `limit` is capped by `SqliteModel::max_limit`, which also applies when no `limit`
+/// is given.
+///
+/// Fields are the names the model serializes with, which must be declared in `M::columns`
+/// when the model declares its columns. Values are converted to the affinity of their column,
+/// and are always bound as parameters.
+///
+/// # Examples
+/// `GET /users?name=alice&created_at[gte]=1700000000&sort=-id&limit=20`
+/// ```ignore
+/// async fn list(
+///     State(pool): State<SqlitePool>,
+///     query: ModelQuery<User>,
+/// ) -> Result<Json<Vec<User>>, Error> {
+///     Ok(Json(query.fetch(&pool).await?))
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelQuery<M> {
+    pub filter: Filter,
+    pub options: SelectOptions,
+    _model: PhantomData<fn() -> M>,
+}
+
+impl<M: SqliteModel> ModelQuery<M> {
+    /// Parse the `name=value` pairs of a query string
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidParameter` if a parameter names an unknown field or operator, a
+    /// value cannot be converted to the type of its column, or `limit` is above the maximum of
+    /// the model
+    pub fn from_pairs(pairs: &[(String, String)]) -> Result<Self, Error> {
+        let mut filter = Filter::all();
+        let mut options = SelectOptions::new();
+        for (name, raw) in pairs {
+            match name.as_str() {
+                "sort" => {
+                    for term in raw.split(',').filter(|t| !t.is_empty()) {
+                        let (field, order) = match term.strip_prefix('-') {
+                            Some(field) => (field, Order::Desc),
+                            None => (term, Order::Asc),
+                        };
+                        options = options.order_by(query_column::<M>(field)?.0, order);
+                    }
+                }
+                "limit" => options = options.limit(parse_count(name, raw)?),
+                "offset" => options = options.offset(parse_count(name, raw)?),
+                _ => filter = filter.and(parse_condition::<M>(name, raw)?.into()),
+            }
+        }
+        if let Some(max) = M::max_limit() {
+            match options.limit {
+                Some(limit) if limit > max => {
+                    return Err(Error::InvalidParameter(format!(
+                        "limit must be at most {}, found {}",
+                        max, limit
+                    )))
+                }
+                Some(_) => {}
+                None => options.limit = Some(max),
+            }
+        }
+        Ok(ModelQuery {
+            filter,
+            options,
+            _model: PhantomData,
+        })
+    }
+
+    /// Selects every record of `M` which matches the query, as `SqliteModel::select_where_with`
+    pub async fn fetch<'e, E>(&self, executor: E) -> Result<Vec<M>, M::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+        M: for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
+    {
+        M::select_where_with(executor, &self.filter, &self.options).await
+    }
+}
+
+impl<M, S> FromRequestParts<S> for ModelQuery<M>
+where
+    M: SqliteModel,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Query(pairs) = Query::<Vec<(String, String)>>::try_from_uri(&parts.uri)
+            .map_err(|e| Error::InvalidParameter(e.body_text()))?;
+        Self::from_pairs(&pairs)
+    }
+}
+
+/// The record of `M` whose primary key is the `{id}` of the request path, loaded with
+/// `SqliteModel::select_one` using the `sqlx::SqlitePool` in the state of the router.
+///
+/// The key is taken from the path parameter named after the primary key column, or `id`, or
+/// the only parameter of the path. Records are identified the same way as by `crud_router`.
+/// A missing record is rejected with `Error::NotFound`, and a path with no such parameter with
+/// `Error::InvalidParameter`, as `M::Error`.
+///
+/// # Examples
+/// ```ignore
+/// // .route("/users/{id}", get(show))
+/// async fn show(Loaded(user): Loaded<User>) -> Json<User> {
+///     Json(user)
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Loaded<M>(pub M);
+
+impl<M, S> FromRequestParts<S> for Loaded<M>
+where
+    M: SqliteModel + for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
+    M::Error: IntoResponse,
+    S: Send + Sync,
+    SqlitePool: FromRef<S>,
+{
+    type Rejection = M::Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(params) = Path::<Vec<(String, String)>>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| Error::InvalidParameter(e.body_text()))?;
+        let key_col = key_column::<M>();
+        let key = match params
+            .iter()
+            .find(|(name, _)| name == key_col || name == "id")
+        {
+            Some((_, key)) => key.clone(),
+            None => match params.as_slice() {
+                [(_, key)] => key.clone(),
+                _ => {
+                    return Err(Error::InvalidParameter(format!(
+                        "no {} parameter in the path to load {} with",
+                        key_col,
+                        M::table_name()
+                    )))?
+                }
+            },
+        };
+        let pool = SqlitePool::from_ref(state);
+        let model = M::select_one(&pool, key_col, key_value::<M>(key)).await?;
+        Ok(Loaded(model))
+    }
+}
+
+/// The column and affinity of the field `field` of `M`. Models which do not declare their
+/// columns accept any valid identifier, with no affinity
+fn query_column<M: SqliteModel>(field: &str) -> Result<(&str, Option<Affinity>), Error> {
+    let columns = M::columns();
+    if columns.is_empty() {
+        quote_ident(field).map_err(|_| unknown_field::<M>(field))?;
+        return Ok((field, None));
+    }
+    match columns.iter().find(|c| c.field == field) {
+        Some(c) => Ok((c.name, c.affinity)),
+        None => Err(unknown_field::<M>(field)),
+    }
+}
+
+pub(crate) fn unknown_field<M: SqliteModel>(field: &str) -> Error {
+    Error::InvalidParameter(format!("{:?} is not a field of {}", field, M::table_name()))
+}
+
+/// Parse a `limit` or `offset`, which SQLite reads as a signed 64 bit integer
+fn parse_count(name: &str, raw: &str) -> Result<u64, Error> {
+    match raw.parse::<i64>() {
+        Ok(count) if count >= 0 => Ok(count as u64),
+        _ => Err(Error::InvalidParameter(format!(
+            "{} must be an integer from 0 to {}, found {:?}",
+            name,
+            i64::MAX,
+            raw
+        ))),
+    }
+}
+
+/// Parse a `field=value` or `field[op]=value` parameter
+fn parse_condition<M: SqliteModel>(name: &str, raw: &str) -> Result<Condition, Error> {
+    let (field, op) = match name.strip_suffix(']').and_then(|n| n.split_once('[')) {
+        Some((field, op)) => (field, op),
+        None => (name, "eq"),
+    };
+    let (column, affinity) = query_column::<M>(field)?;
+    let val = |raw| coerce(affinity, field, raw);
+    let op = match op {
+        "eq" => Operator::Eq(val(raw)?),
+        "ne" => Operator::Ne(val(raw)?),
+        "lt" => Operator::Lt(val(raw)?),
+        "lte" => Operator::Le(val(raw)?),
+        "gt" => Operator::Gt(val(raw)?),
+        "gte" => Operator::Ge(val(raw)?),
+        "like" => Operator::Like(Value::from(raw)),
+        "in" => Operator::In(raw.split(',').map(val).collect::<Result<_, _>>()?),
+        "null" => match raw {
+            "true" => Operator::IsNull,
+            "false" => Operator::IsNotNull,
+            _ => {
+                return Err(Error::InvalidParameter(format!(
+                    "{}[null] must be true or false, found {:?}",
+                    field, raw
+                )))
+            }
+        },
+        _ => {
+            return Err(Error::InvalidParameter(format!(
+                "{:?} is not a filter operator",
+                op
+            )))
+        }
+    };
+    Ok(Condition::new(column, op))
+}
+
+/// Convert the query string value `raw` of `field` to a value with the affinity of its column.
+/// Columns with numeric or unknown affinity hold whichever of integer, real or text `raw`
+/// parses as first
+fn coerce(affinity: Option<Affinity>, field: &str, raw: &str) -> Result<Value, Error> {
+    let invalid =
+        |kind| Error::InvalidParameter(format!("{} must be {}, found {:?}", field, kind, raw));
+    match affinity {
+        Some(Affinity::Integer) => match raw {
+            "true" => Ok(Value::from(1)),
+            "false" => Ok(Value::from(0)),
+            _ => raw
+                .parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| invalid("an integer")),
+        },
+        Some(Affinity::Real) => match raw.parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(Value::from(f)),
+            _ => Err(invalid("a number")),
+        },
+        Some(Affinity::Text | Affinity::Blob) => Ok(Value::from(raw)),
+        Some(Affinity::Numeric) | None => {
+            if let Ok(i) = raw.parse::<i64>() {
+                return Ok(Value::from(i));
+            }
+            match raw.parse::<f64>() {
+                Ok(f) if f.is_finite() => Ok(Value::from(f)),
+                _ => Ok(Value::from(raw)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        routing::get,
+        Json, Router,
+    };
+    use serde::Serialize;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::{Loaded, ModelQuery};
+    use crate::{Error, Filter, IfNotExists, Order, SelectOptions, SqliteModel};
+
+    #[derive(Debug, Serialize, crate::SqliteModel)]
+    #[model(table = "users")]
+    struct User {
+        #[model(primary_key, skip_insert)]
+        id: i64,
+        #[model(rename = "user_name")]
+        name: String,
+        score: Option<f64>,
+        created_at: i64,
+    }
+
+    fn parse(query: &str) -> Result<ModelQuery<User>, Error> {
+        let pairs: Vec<(String, String)> = query
+            .split('&')
+            .filter_map(|p| p.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        ModelQuery::from_pairs(&pairs)
+    }
+
+    #[test]
+    fn test_from_pairs() {
+        let query = parse("name=alice&created_at[gte]=1700000000&sort=-id,name&limit=20").unwrap();
+        assert_eq!(
+            query.filter,
+            Filter::all()
+                .and(Filter::eq("user_name", "alice"))
+                .and(Filter::ge("created_at", 1700000000))
+        );
+        assert_eq!(
+            query.options,
+            SelectOptions::new()
+                .order_by("id", Order::Desc)
+                .order_by("user_name", Order::Asc)
+                .limit(20)
+        );
+
+        let query = parse("id[in]=1,2&score[lt]=2.5&name[null]=false&offset=5").unwrap();
+        assert_eq!(
+            query.filter,
+            Filter::all()
+                .and(Filter::is_in("id", [1, 2]))
+                .and(Filter::lt("score", json!(2.5)))
+                .and(Filter::is_not_null("user_name"))
+        );
+        assert_eq!(query.options, SelectOptions::new().offset(5));
+
+        let query = parse("name=42").unwrap();
+        assert_eq!(
+            query.filter,
+            Filter::all().and(Filter::eq("user_name", "42"))
+        );
+    }
+
+    #[test]
+    fn test_from_pairs_invalid() {
+        for query in [
+            "user_name=alice",
+            "passwd=x",
+            "id;drop table users=1",
+            "sort=-passwd",
+            "id=abc",
+            "score[gt]=high",
+            "id[regex]=1",
+            "name[null]=maybe",
+            "limit=-1",
+            "limit=9223372036854775808",
+            "offset=18446744073709551615",
+        ] {
+            match parse(query) {
+                Err(Error::InvalidParameter(_)) => {}
+                res => panic!("{}: expected an invalid parameter, found {:?}", query, res),
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize, crate::SqliteModel)]
+    #[model(table = "posts", max_limit = 50)]
+    struct Post {
+        #[model(primary_key)]
+        id: i64,
+    }
+
+    #[test]
+    fn test_max_limit() {
+        let query = ModelQuery::<Post>::from_pairs(&[]).unwrap();
+        assert_eq!(query.options.limit, Some(50));
+        let limit = |raw: &str| {
+            let pairs = [("limit".to_string(), raw.to_string())];
+            ModelQuery::<Post>::from_pairs(&pairs).map(|q| q.options.limit)
+        };
+        assert_eq!(limit("50").unwrap(), Some(50));
+        assert_eq!(limit("10").unwrap(), Some(10));
+        assert!(matches!(limit("51"), Err(Error::InvalidParameter(_))));
+        assert_eq!(parse("").unwrap().options.limit, None);
+        assert_eq!(
+            parse("limit=9223372036854775807").unwrap().options.limit,
+            Some(i64::MAX as u64)
+        );
+    }
+
+    #[derive(Debug, Serialize, crate::SqliteModel)]
+    #[model(table = "accounts")]
+    #[serde(rename_all = "camelCase")]
+    struct Account {
+        #[model(primary_key)]
+        id: i64,
+        display_name: String,
+        #[serde(rename = "mail", skip_serializing_if = "Option::is_none")]
+        email_address: Option<String>,
+    }
+
+    #[test]
+    fn test_serde_names() {
+        let pairs = [
+            ("displayName".to_string(), "alice".to_string()),
+            ("mail[null]".to_string(), "true".to_string()),
+            ("sort".to_string(), "-displayName".to_string()),
+        ];
+        let query = ModelQuery::<Account>::from_pairs(&pairs).unwrap();
+        assert_eq!(
+            query.filter,
+            Filter::all()
+                .and(Filter::eq("display_name", "alice"))
+                .and(Filter::is_null("email_address"))
+        );
+        assert_eq!(
+            query.options,
+            SelectOptions::new().order_by("display_name", Order::Desc)
+        );
+        for field in ["display_name", "email_address", "emailAddress"] {
+            let pairs = [(field.to_string(), "x".to_string())];
+            assert!(matches!(
+                ModelQuery::<Account>::from_pairs(&pairs),
+                Err(Error::InvalidParameter(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        User::create_table(&pool, IfNotExists(false)).await.unwrap();
+        for (i, name) in ["alice", "bob", "carol", "alice"].iter().enumerate() {
+            let user = User {
+                id: 0,
+                name: name.to_string(),
+                score: None,
+                created_at: i as i64,
+            };
+            user.insert(&pool, &[]).await.unwrap();
+        }
+
+        let users = parse("name=alice&sort=-id")
+            .unwrap()
+            .fetch(&pool)
+            .await
+            .unwrap();
+        assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), [4, 1]);
+        let users = parse("created_at[gte]=1&limit=2")
+            .unwrap()
+            .fetch(&pool)
+            .await
+            .unwrap();
+        assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), [2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_loaded() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        User::create_table(&pool, IfNotExists(false)).await.unwrap();
+        let user = User {
+            id: 0,
+            name: "alice".to_string(),
+            score: Some(1.5),
+            created_at: 0,
+        };
+        user.insert(&pool, &[]).await.unwrap();
+
+        async fn show(Loaded(user): Loaded<User>) -> Json<String> {
+            Json(user.name)
+        }
+        async fn show_nested(Loaded(user): Loaded<User>) -> Json<i64> {
+            Json(user.id)
+        }
+        let app = Router::new()
+            .route("/users/{id}", get(show))
+            .route("/orgs/{org}/users/{id}", get(show_nested))
+            .route("/by/{a}/{b}", get(show))
+            .with_state(pool);
+        let status = |uri: &str| {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let app = app.clone();
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+
+        assert_eq!(status("/users/1").await, StatusCode::OK);
+        assert_eq!(status("/orgs/7/users/1").await, StatusCode::OK);
+        assert_eq!(status("/users/2").await, StatusCode::NOT_FOUND);
+        assert_eq!(status("/users/alice").await, StatusCode::NOT_FOUND);
+        assert_eq!(status("/by/1/2").await, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,377 @@
+use serde_json::Value;
+
+use crate::{ident::quote_column, BasicType, Column, Error};
+
+/// The comparison applied to a single column by a [`Condition`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operator {
+    Eq(Value),
+    Ne(Value),
+    Lt(Value),
+    Le(Value),
+    Gt(Value),
+    Ge(Value),
+    Like(Value),
+    NotLike(Value),
+    In(Vec<Value>),
+    NotIn(Vec<Value>),
+    IsNull,
+    IsNotNull,
+    Between(Value, Value),
+}
+
+/// A single predicate against one column, eg `age >= ?`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub column: String,
+    pub op: Operator,
+}
+
+impl Condition {
+    pub fn new(column: &str, op: Operator) -> Self {
+        Condition {
+            column: column.to_string(),
+            op,
+        }
+    }
+
+    fn write_sql(
+        &self,
+        sql: &mut String,
+        vals: &mut Vec<BasicType>,
+        columns: &[Column],
+    ) -> Result<(), Error> {
+        let col = &quote_column(&self.column, columns)?;
+        let basic = |v: &Value| BasicType::from_json(columns, &self.column, v);
+        match &self.op {
+            Operator::Eq(v) => push_binary(sql, vals, col, "=", basic(v)?),
+            Operator::Ne(v) => push_binary(sql, vals, col, "!=", basic(v)?),
+            Operator::Lt(v) => push_binary(sql, vals, col, "<", basic(v)?),
+            Operator::Le(v) => push_binary(sql, vals, col, "<=", basic(v)?),
+            Operator::Gt(v) => push_binary(sql, vals, col, ">", basic(v)?),
+            Operator::Ge(v) => push_binary(sql, vals, col, ">=", basic(v)?),
+            Operator::Like(v) => push_binary(sql, vals, col, "like", basic(v)?),
+            Operator::NotLike(v) => push_binary(sql, vals, col, "not like", basic(v)?),
+            Operator::In(list) => {
+                let list = list.iter().map(basic).collect::<Result<_, _>>()?;
+                push_list(sql, vals, col, "in", list, "0 = 1")
+            }
+            Operator::NotIn(list) => {
+                let list = list.iter().map(basic).collect::<Result<_, _>>()?;
+                push_list(sql, vals, col, "not in", list, "1 = 1")
+            }
+            Operator::IsNull => sql.push_str(&format!("{} is null", col)),
+            Operator::IsNotNull => sql.push_str(&format!("{} is not null", col)),
+            Operator::Between(low, high) => {
+                sql.push_str(&format!("{} between ? and ?", col));
+                vals.push(basic(low)?);
+                vals.push(basic(high)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn push_binary(sql: &mut String, vals: &mut Vec<BasicType>, col: &str, op: &str, val: BasicType) {
+    sql.push_str(&format!("{} {} ?", col, op));
+    vals.push(val);
+}
+
+/// Render `col in (?, ?, ...)`. An empty list cannot be expressed in SQL, so `empty` is
+/// rendered in its place instead
+fn push_list(
+    sql: &mut String,
+    vals: &mut Vec<BasicType>,
+    col: &str,
+    op: &str,
+    list: Vec<BasicType>,
+    empty: &str,
+) {
+    if list.is_empty() {
+        sql.push_str(empty);
+        return;
+    }
+    let qmarks = vec!["?"; list.len()];
+    sql.push_str(&format!("{} {} ({})", col, op, qmarks.join(",")));
+    vals.extend(list);
+}
+
+/// A tree of [`Condition`]s joined by `and`, `or` and `not`, which renders to the `where`
+/// clause of a query with every value passed as a bound parameter
+///
+/// # Examples
+/// ```
+/// use sqlx_model::Filter;
+///
+/// let filter = Filter::ge("created_at", 1700000000)
+///     .and(Filter::like("name", "a%").or(Filter::is_null("name")));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Condition(Condition),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    /// A filter which matches every row
+    pub fn all() -> Self {
+        Filter::And(Vec::new())
+    }
+
+    pub fn eq(col: &str, val: impl Into<Value>) -> Self {
+        Filter::Condition(Condition::new(col, Operator::Eq(val.into())))
+    }
+
+    pub fn ne(col: &str, val: impl Into<Value>) -> Self {
+        Filter::Condition(Condition::new(col, Operator::Ne(val.into())))
+    }
+
+    pub fn lt(col: &str, val: impl Into<Value>) -> Self {
+        Filter::Condition(Condition::new(col, Operator::Lt(val.into())))
+    }
+
+    pub fn le(col: &str, val: impl Into<Value>) -> Self {
+        Filter::Condition(Condition::new(col, Operator::Le(val.into())))
+    }
+
+    pub fn gt(col: &str, val: impl Into<Value>) -> Self {
+        Filter::Condition(Condition::new(col, Operator::Gt(val.into())))
+    }
+
+    pub fn ge(col: &str, val: impl Into<Value>) -> Self {
+        Filter::Condition(Condition::new(col, Operator::Ge(val.into())))
+    }
+
+    pub fn like(col: &str, pattern: impl Into<Value>) -> Self {
+        Filter::Condition(Condition::new(col, Operator::Like(pattern.into())))
+    }
+
+    pub fn not_like(col: &str, pattern: impl Into<Value>) -> Self {
+        Filter::Condition(Condition::new(col, Operator::NotLike(pattern.into())))
+    }
+
+    pub fn is_in<I, V>(col: &str, vals: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        let vals = vals.into_iter().map(Into::into).collect();
+        Filter::Condition(Condition::new(col, Operator::In(vals)))
+    }
+
+    pub fn not_in<I, V>(col: &str, vals: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        let vals = vals.into_iter().map(Into::into).collect();
+        Filter::Condition(Condition::new(col, Operator::NotIn(vals)))
+    }
+
+    pub fn is_null(col: &str) -> Self {
+        Filter::Condition(Condition::new(col, Operator::IsNull))
+    }
+
+    pub fn is_not_null(col: &str) -> Self {
+        Filter::Condition(Condition::new(col, Operator::IsNotNull))
+    }
+
+    pub fn between(col: &str, low: impl Into<Value>, high: impl Into<Value>) -> Self {
+        Filter::Condition(Condition::new(
+            col,
+            Operator::Between(low.into(), high.into()),
+        ))
+    }
+
+    /// Combine `self` and `other` so that both must match. Chained calls are flattened into a
+    /// single `and` group
+    pub fn and(self, other: Filter) -> Self {
+        match self {
+            Filter::And(mut filters) => {
+                filters.push(other);
+                Filter::And(filters)
+            }
+            filter => Filter::And(vec![filter, other]),
+        }
+    }
+
+    /// Combine `self` and `other` so that either may match. Chained calls are flattened into a
+    /// single `or` group
+    pub fn or(self, other: Filter) -> Self {
+        match self {
+            Filter::Or(mut filters) => {
+                filters.push(other);
+                Filter::Or(filters)
+            }
+            filter => Filter::Or(vec![filter, other]),
+        }
+    }
+
+    /// Render the filter as the body of a `where` clause
+    ///
+    /// # Arguments
+    /// - columns: The known columns of the table. Every column referenced by the filter must be
+    ///   one of them, unless the slice is empty.
+    ///
+    /// # Returns
+    /// - (String, Vec<BasicType>): The SQL fragment and the values to bind to its placeholders,
+    ///   in order
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidIdentifier` or `Error::UnknownColumn` if a referenced column is not
+    /// a valid identifier, or is not one of `columns`, and `Error::UnsupportedValue` if a value
+    /// cannot be stored in SQLite
+    pub fn to_sql(&self, columns: &[Column]) -> Result<(String, Vec<BasicType>), Error> {
+        let mut sql = String::new();
+        let mut vals = Vec::new();
+        self.write_sql(&mut sql, &mut vals, columns)?;
+        Ok((sql, vals))
+    }
+
+    fn write_sql(
+        &self,
+        sql: &mut String,
+        vals: &mut Vec<BasicType>,
+        columns: &[Column],
+    ) -> Result<(), Error> {
+        match self {
+            Filter::Condition(cond) => cond.write_sql(sql, vals, columns)?,
+            Filter::And(filters) => write_group(sql, vals, columns, filters, " and ", "1 = 1")?,
+            Filter::Or(filters) => write_group(sql, vals, columns, filters, " or ", "0 = 1")?,
+            Filter::Not(filter) => {
+                sql.push_str("not (");
+                filter.write_sql(sql, vals, columns)?;
+                sql.push(')');
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_group(
+    sql: &mut String,
+    vals: &mut Vec<BasicType>,
+    columns: &[Column],
+    filters: &[Filter],
+    sep: &str,
+    empty: &str,
+) -> Result<(), Error> {
+    if filters.is_empty() {
+        sql.push_str(empty);
+        return Ok(());
+    }
+    for (i, filter) in filters.iter().enumerate() {
+        if i > 0 {
+            sql.push_str(sep);
+        }
+        sql.push('(');
+        filter.write_sql(sql, vals, columns)?;
+        sql.push(')');
+    }
+    Ok(())
+}
+
+impl std::ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Self::Output {
+        Filter::Not(Box::new(self))
+    }
+}
+
+impl From<Condition> for Filter {
+    fn from(value: Condition) -> Self {
+        Filter::Condition(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Filter;
+    use crate::{BasicType, Column, Error};
+
+    #[test]
+    fn test_to_sql_condition() {
+        let (sql, vals) = Filter::eq("name", "alice").to_sql(&[]).unwrap();
+        assert_eq!(sql, "\"name\" = ?");
+        assert_eq!(vals, vec![BasicType::Text("alice".to_string())]);
+
+        let (sql, vals) = Filter::between("age", 18, 65).to_sql(&[]).unwrap();
+        assert_eq!(sql, "\"age\" between ? and ?");
+        assert_eq!(vals, vec![BasicType::Integer(18), BasicType::Integer(65)]);
+
+        let (sql, vals) = Filter::is_null("deleted").to_sql(&[]).unwrap();
+        assert_eq!(sql, "\"deleted\" is null");
+        assert!(vals.is_empty());
+    }
+
+    #[test]
+    fn test_to_sql_in() {
+        let (sql, vals) = Filter::is_in("id", [1, 2, 3]).to_sql(&[]).unwrap();
+        assert_eq!(sql, "\"id\" in (?,?,?)");
+        assert_eq!(
+            vals,
+            vec![
+                BasicType::Integer(1),
+                BasicType::Integer(2),
+                BasicType::Integer(3)
+            ]
+        );
+
+        let (sql, vals) = Filter::is_in("id", Vec::<i64>::new()).to_sql(&[]).unwrap();
+        assert_eq!(sql, "0 = 1");
+        assert!(vals.is_empty());
+    }
+
+    #[test]
+    fn test_to_sql_groups() {
+        let filter = Filter::ge("age", 18)
+            .and(Filter::like("name", "a%").or(Filter::is_null("name")))
+            .and(!Filter::eq("id", 4));
+        let (sql, vals) = filter.to_sql(&[]).unwrap();
+        assert_eq!(
+            sql,
+            "(\"age\" >= ?) and ((\"name\" like ?) or (\"name\" is null)) and (not (\"id\" = ?))"
+        );
+        assert_eq!(
+            vals,
+            vec![
+                BasicType::Integer(18),
+                BasicType::Text("a%".to_string()),
+                BasicType::Integer(4)
+            ]
+        );
+
+        assert_eq!(Filter::all().to_sql(&[]).unwrap().0, "1 = 1");
+        assert_eq!(Filter::Or(Vec::new()).to_sql(&[]).unwrap().0, "0 = 1");
+    }
+
+    #[test]
+    fn test_to_sql_columns() {
+        let columns = [Column::new("id"), Column::new("name")];
+        let filter = Filter::eq("id", 1).or(Filter::eq("name", "bob"));
+        assert!(filter.to_sql(&columns).is_ok());
+
+        let filter = Filter::eq("id", 1).or(Filter::eq("email", "bob@example.com"));
+        assert!(filter.to_sql(&columns).is_err());
+        assert!(filter.to_sql(&[]).is_ok());
+
+        assert!(Filter::is_null("1 = 1 or name").to_sql(&[]).is_err());
+    }
+
+    #[test]
+    fn test_to_sql_unsupported_value() {
+        let filter = Filter::eq("id", u64::MAX).and(Filter::eq("name", "bob"));
+        match filter.to_sql(&[]) {
+            Err(Error::UnsupportedValue { column, value }) => {
+                assert_eq!(column, "id");
+                assert_eq!(value, json!(u64::MAX));
+            }
+            res => panic!("expected an unsupported value, found {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::{future::Future, pin::Pin};
+
+use async_trait::async_trait;
+
+use crate::{Filter, SqliteModel};
+
+/// Lifecycle hooks run by the default methods of `SqliteModel`, for models whose
+/// `SqliteModel::hooks` returns `Hooks::new()`. Deriving with `#[model(hooks)]` does so.
+///
+/// The `before_*` hooks of a write are called on a copy of each model, so the model passed to
+/// `insert`, `upsert` or `update` is left unchanged. Returning an error from a `before_*` hook
+/// aborts the operation, so nothing is written. Returning an error from an `after_*` hook is
+/// reported to the caller, but does not undo the write unless it ran in a transaction which is
+/// rolled back. Every hook does nothing by default.
+///
+/// # Examples
+/// ```ignore
+/// #[derive(Clone, Serialize, SqliteModel)]
+/// #[model(hooks)]
+/// struct User {
+///     email: String,
+/// }
+///
+/// #[async_trait]
+/// impl ModelHooks for User {
+///     async fn before_insert(&mut self) -> Result<(), Error> {
+///         self.email = self.email.to_lowercase();
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait ModelHooks: SqliteModel + Clone + Send + Sync + 'static {
+    /// Called by `insert` and `insert_many` before each model is written.
+    async fn before_insert(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called by `insert` and `insert_many` with each model as it was saved.
+    async fn after_insert(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called by `upsert` and `upsert_many` before each model is written.
+    async fn before_upsert(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called by `upsert` and `upsert_many` with each model as it was saved.
+    async fn after_upsert(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called by `update` before the model is written.
+    async fn before_update(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called by `update` with the model as it was saved.
+    async fn after_update(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called by `delete`, `delete_where` and `force_delete` with the filter selecting the
+    /// records to delete, before anything is deleted.
+    async fn before_delete(_filter: &Filter) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called by `delete`, `delete_where` and `force_delete` with each deleted model.
+    async fn after_delete(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called by `restore` with the filter selecting the records to restore, before anything
+    /// is restored.
+    async fn before_restore(_filter: &Filter) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called by `restore` with each restored model.
+    async fn after_restore(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+type HookFuture<'a, T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'a>>;
+
+/// The write a hook is run for
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Write {
+    Insert,
+    Upsert,
+    Update,
+    Delete,
+    Restore,
+}
+
+/// The `ModelHooks` of a model, returned by `SqliteModel::hooks` to have them run. Only models
+/// with hooks need to be `Clone`.
+pub struct Hooks<M: SqliteModel> {
+    before: for<'a> fn(&'a M, Write) -> HookFuture<'a, M, M::Error>,
+    after: for<'a> fn(&'a [M], Write) -> HookFuture<'a, (), M::Error>,
+    before_where: for<'a> fn(&'a Filter, Write) -> HookFuture<'a, (), M::Error>,
+}
+
+impl<M: ModelHooks> Hooks<M> {
+    /// The hooks of the `ModelHooks` implementation of `M`
+    pub fn new() -> Self {
+        Hooks {
+            before: before::<M>,
+            after: after::<M>,
+            before_where: before_where::<M>,
+        }
+    }
+}
+
+impl<M: ModelHooks> Default for Hooks<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M: SqliteModel> Hooks<M> {
+    /// A copy of `model` changed by its `before_*` hook for `write`
+    pub(crate) async fn before(&self, model: &M, write: Write) -> Result<M, M::Error> {
+        (self.before)(model, write).await
+    }
+
+    /// Runs the `after_*` hook for `write` on each of `models`
+    pub(crate) async fn after(&self, models: &[M], write: Write) -> Result<(), M::Error> {
+        (self.after)(models, write).await
+    }
+
+    /// Runs the `before_*` hook for `write` with the filter selecting the records it changes
+    pub(crate) async fn before_where(&self, filter: &Filter, write: Write) -> Result<(), M::Error> {
+        (self.before_where)(filter, write).await
+    }
+}
+
+fn before<M: ModelHooks>(model: &M, write: Write) -> HookFuture<'_, M, M::Error> {
+    Box::pin(async move {
+        let mut model = model.clone();
+        match write {
+            Write::Insert => model.before_insert().await?,
+            Write::Upsert => model.before_upsert().await?,
+            Write::Update => model.before_update().await?,
+            Write::Delete | Write::Restore => {}
+        }
+        Ok(model)
+    })
+}
+
+fn after<M: ModelHooks>(models: &[M], write: Write) -> HookFuture<'_, (), M::Error> {
+    Box::pin(async move {
+        for model in models {
+            match write {
+                Write::Insert => model.after_insert().await?,
+                Write::Upsert => model.after_upsert().await?,
+                Write::Update => model.after_update().await?,
+                Write::Delete => model.after_delete().await?,
+                Write::Restore => model.after_restore().await?,
+            }
+        }
+        Ok(())
+    })
+}
+
+fn before_where<M: ModelHooks>(filter: &Filter, write: Write) -> HookFuture<'_, (), M::Error> {
+    match write {
+        Write::Restore => M::before_restore(filter),
+        _ => M::before_delete(filter),
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{Column, Error};
+
+/// Check that `name` is a plain SQL identifier: an ASCII letter or underscore followed by any
+/// number of ASCII letters, digits or underscores
+pub fn is_valid_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// Validate `name` and wrap it in double quotes so it is always treated as an identifier,
+/// even if it collides with an SQL keyword
+///
+/// # Errors
+/// Returns `Error::InvalidIdentifier` if `name` is not a valid identifier according to
+/// [`is_valid_ident`]
+pub fn quote_ident(name: &str) -> Result<String, Error> {
+    if !is_valid_ident(name) {
+        return Err(Error::InvalidIdentifier(name.to_string()));
+    }
+    Ok(format!("\"{}\"", name))
+}
+
+/// Quote `name` with [`quote_ident`], and additionally check that it is one of `columns`. An
+/// empty `columns` slice means the columns of the model are unknown, so only the identifier
+/// itself is validated
+pub(crate) fn quote_column(name: &str, columns: &[Column]) -> Result<String, Error> {
+    let quoted = quote_ident(name)?;
+    if !columns.is_empty() && !columns.iter().any(|c| c.name == name) {
+        return Err(Error::UnknownColumn(name.to_string()));
+    }
+    Ok(quoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quote_column, quote_ident};
+    use crate::{Column, Error};
+
+    #[test]
+    fn test_quote_ident() {
+        assert_eq!(quote_ident("name").unwrap(), "\"name\"");
+        assert_eq!(quote_ident("_created_at2").unwrap(), "\"_created_at2\"");
+        assert_eq!(quote_ident("order").unwrap(), "\"order\"");
+
+        assert!(matches!(quote_ident(""), Err(Error::InvalidIdentifier(_))));
+        assert!(quote_ident("2fast").is_err());
+        assert!(quote_ident("name\"").is_err());
+        assert!(quote_ident("id = 1 or 1 = 1; --").is_err());
+        assert!(quote_ident("users.name").is_err());
+    }
+
+    #[test]
+    fn test_quote_column() {
+        let columns = [Column::new("id"), Column::new("name")];
+        assert_eq!(quote_column("name", &columns).unwrap(), "\"name\"");
+        assert!(matches!(
+            quote_column("email", &columns),
+            Err(Error::UnknownColumn(_))
+        ));
+        assert_eq!(quote_column("email", &[]).unwrap(), "\"email\"");
+    }
+}
@@ -1,10 +1,51 @@
+extern crate self as sqlx_model;
+
+mod aggregate;
+mod error;
+#[cfg(feature = "axum")]
+mod extract;
+mod filter;
+mod hooks;
+mod ident;
+mod migrations;
+#[cfg(feature = "openapi")]
+mod openapi;
+mod options;
+mod page;
+#[cfg(feature = "axum")]
+mod router;
+mod schema;
 mod sqlite;
+mod validate;
 
+pub use aggregate::Aggregate;
+pub use error::{ConstraintKind, Error};
+#[cfg(feature = "axum")]
+pub use extract::{Loaded, ModelQuery};
+pub use filter::{Condition, Filter, Operator};
+pub use hooks::{Hooks, ModelHooks};
+pub use ident::{is_valid_ident, quote_ident};
+pub use migrations::{diff_migration, generate_migration, Migration, Migrator, MIGRATIONS_TABLE};
+#[cfg(feature = "openapi")]
+pub use openapi::OpenApi;
+pub use options::{Order, SelectOptions};
+pub use page::Page;
+#[cfg(feature = "axum")]
+pub use router::{crud_router, CrudRoute, CrudRouter};
+pub use schema::{verify_schema, IfNotExists, Mismatch, SchemaDiff};
 pub use sqlite::SqliteModel;
+pub use sqlx_model_derive::SqliteModel;
+pub use validate::{FieldError, FieldRule, Rule};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json;
+    pub use sqlx;
+}
 
 use std::collections::HashMap;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BasicType {
     Null,
     Integer(i64),
@@ -13,6 +54,69 @@ pub enum BasicType {
     Blob(Vec<u8>),
 }
 
+impl BasicType {
+    /// Convert the serialized value of `column` into the type SQLite stores it as.
+    ///
+    /// Objects and arrays are stored as JSON text, except for arrays of bytes in a column with
+    /// the `Blob` affinity, which are stored as blobs. When `columns` does not declare `column`,
+    /// any array of bytes is stored as a blob. A column marked `json` stores any non-null value
+    /// as JSON text
+    ///
+    /// # Errors
+    /// Returns `Error::UnsupportedValue` if `val` has no SQLite equivalent
+    pub(crate) fn from_json(
+        columns: &[Column],
+        column: &str,
+        val: &serde_json::Value,
+    ) -> Result<Self, Error> {
+        let declared = columns.iter().find(|c| c.name == column);
+        let json_column = declared.is_some_and(|c| c.json);
+        let blob_column = declared.is_none_or(|c| c.affinity == Some(Affinity::Blob));
+        let basic = match val {
+            serde_json::Value::Null => Some(BasicType::Null),
+            _ if json_column => Some(BasicType::Text(val.to_string())),
+            serde_json::Value::Bool(b) => Some(BasicType::from(*b)),
+            serde_json::Value::Number(_) => val_to_basic_num(val),
+            serde_json::Value::String(s) => Some(BasicType::Text(s.to_string())),
+            serde_json::Value::Array(a) => match val_to_blob(a).filter(|_| blob_column) {
+                Some(blob) => Some(BasicType::Blob(blob)),
+                None => Some(BasicType::Text(val.to_string())),
+            },
+            serde_json::Value::Object(_) => Some(BasicType::Text(val.to_string())),
+        };
+        basic.ok_or(Error::UnsupportedValue {
+            column: column.to_string(),
+            value: val.to_owned(),
+        })
+    }
+}
+
+fn val_to_blob(arr: &[serde_json::Value]) -> Option<Vec<u8>> {
+    let mut blob = Vec::new();
+    for el in arr {
+        let basic = val_to_basic_num(el)?;
+        match basic {
+            BasicType::Integer(i) => blob.push(u8::try_from(i).ok()?),
+            _ => return None,
+        }
+    }
+    Some(blob)
+}
+
+fn val_to_basic_num(val: &serde_json::Value) -> Option<BasicType> {
+    if let serde_json::Value::Number(num) = val {
+        if let Some(n) = num.as_i64() {
+            return Some(BasicType::Integer(n));
+        }
+        // Integers past `i64::MAX` would silently lose precision as a REAL
+        if num.is_f64() {
+            return num.as_f64().map(BasicType::Real);
+        }
+        return None;
+    }
+    None
+}
+
 impl From<bool> for BasicType {
     fn from(value: bool) -> Self {
         match value {
@@ -108,5 +212,198 @@ where
 
 pub type ColumnValueMap = HashMap<String, BasicType>;
 
+/// The type affinity SQLite gives a column, which decides how stored values are converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affinity {
+    Integer,
+    Real,
+    Text,
+    Blob,
+    Numeric,
+}
+
+impl Affinity {
+    /// The type name to declare a column of this affinity with
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Affinity::Integer => "integer",
+            Affinity::Real => "real",
+            Affinity::Text => "text",
+            Affinity::Blob => "blob",
+            Affinity::Numeric => "numeric",
+        }
+    }
+}
+
+/// How a timestamp column stores the time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// An integer number of seconds since the unix epoch
+    UnixSeconds,
+    /// An integer number of milliseconds since the unix epoch
+    UnixMillis,
+    /// ISO-8601 text in UTC with millisecond precision, eg `2024-05-01T09:30:00.000Z`
+    Iso8601,
+}
+
+impl TimeFormat {
+    /// The SQL expression evaluating to the current time in this format
+    pub const fn now_sql(&self) -> &'static str {
+        match self {
+            TimeFormat::UnixSeconds => "unixepoch()",
+            TimeFormat::UnixMillis => "cast(unixepoch('subsec') * 1000 as integer)",
+            TimeFormat::Iso8601 => "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')",
+        }
+    }
+}
+
+/// A column which the crate keeps set to the time its record was written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timestamp {
+    /// The time the record was inserted, left to the default of the column and never written
+    /// by `upsert` or `update`
+    CreatedAt(TimeFormat),
+    /// The time the record was last written, set to the current time by every `upsert` and
+    /// `update`
+    UpdatedAt(TimeFormat),
+    /// The time the record was soft deleted, or `NULL` while it is not. Only written by
+    /// `delete` and `restore`
+    DeletedAt(TimeFormat),
+}
+
+/// Metadata describing one column of a model's table, known at compile time when the model
+/// derives `SqliteModel`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Column {
+    /// The name of the column in the database
+    pub name: &'static str,
+    /// The name the struct field the column maps to is serialized with, following its
+    /// `#[serde(rename)]` or the struct's `#[serde(rename_all)]`
+    pub field: &'static str,
+    pub primary_key: bool,
+    /// Leave the column out of `insert` statements so the database default applies
+    pub skip_insert: bool,
+    /// Store the value as JSON text, validated by SQLite's `json()`, even if it would otherwise
+    /// be stored as a blob
+    pub json: bool,
+    /// The declared type of the column. `None` declares the column without a type
+    pub affinity: Option<Affinity>,
+    /// Whether the field is a `bool`, which SQLite stores as the integer `0` or `1`
+    pub boolean: bool,
+    pub not_null: bool,
+    pub unique: bool,
+    /// An SQL expression the database uses as the value when none is inserted, eg
+    /// `unixepoch()` or `'pending'`
+    pub default: Option<&'static str>,
+    /// Whether the column records when the record was created or last written
+    pub timestamp: Option<Timestamp>,
+    /// Whether the column counts the writes to the record, for optimistic locking
+    pub version: bool,
+}
+
+impl Column {
+    pub const fn new(name: &'static str) -> Self {
+        Column {
+            name,
+            field: name,
+            primary_key: false,
+            skip_insert: false,
+            json: false,
+            affinity: None,
+            boolean: false,
+            not_null: false,
+            unique: false,
+            default: None,
+            timestamp: None,
+            version: false,
+        }
+    }
+
+    pub const fn field(mut self, field: &'static str) -> Self {
+        self.field = field;
+        self
+    }
+
+    pub const fn primary_key(mut self) -> Self {
+        self.primary_key = true;
+        self
+    }
+
+    pub const fn skip_insert(mut self) -> Self {
+        self.skip_insert = true;
+        self
+    }
+
+    pub const fn json(mut self) -> Self {
+        self.json = true;
+        self
+    }
+
+    pub const fn affinity(mut self, affinity: Affinity) -> Self {
+        self.affinity = Some(affinity);
+        self
+    }
+
+    pub const fn boolean(mut self) -> Self {
+        self.boolean = true;
+        self
+    }
+
+    pub const fn not_null(mut self) -> Self {
+        self.not_null = true;
+        self
+    }
+
+    pub const fn unique(mut self) -> Self {
+        self.unique = true;
+        self
+    }
+
+    pub const fn default_expr(mut self, expr: &'static str) -> Self {
+        self.default = Some(expr);
+        self
+    }
+
+    /// Mark the column as the time the record was created. It is skipped on insert, and
+    /// defaults to the current time in `format` unless another default is given
+    pub const fn created_at(self, format: TimeFormat) -> Self {
+        self.with_timestamp(Timestamp::CreatedAt(format), Some(format.now_sql()))
+    }
+
+    /// Mark the column as the time the record was last written. It is skipped on insert like
+    /// `created_at`, and set to the current time by every `upsert` and `update`
+    pub const fn updated_at(self, format: TimeFormat) -> Self {
+        self.with_timestamp(Timestamp::UpdatedAt(format), Some(format.now_sql()))
+    }
+
+    /// Mark the column as the time the record was soft deleted, so that `delete` sets it rather
+    /// than removing the record. The column must be nullable, and is skipped on insert
+    pub const fn deleted_at(self, format: TimeFormat) -> Self {
+        self.with_timestamp(Timestamp::DeletedAt(format), None)
+    }
+
+    /// Mark the column as the version of the record, an integer which starts at the default of
+    /// the column, `0` unless another default is given, and is incremented by every `upsert`
+    /// and `update`. Those only write the record if it still has the version of the model they
+    /// were given, and return `Error::Conflict` otherwise
+    pub const fn version(mut self) -> Self {
+        self.version = true;
+        self.skip_insert = true;
+        if self.default.is_none() {
+            self.default = Some("0");
+        }
+        self
+    }
+
+    const fn with_timestamp(mut self, timestamp: Timestamp, default: Option<&'static str>) -> Self {
+        self.timestamp = Some(timestamp);
+        self.skip_insert = true;
+        if self.default.is_none() {
+            self.default = default;
+        }
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {}
@@ -0,0 +1,854 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+use sqlx::{Acquire, Connection};
+
+use crate::{
+    ident::{is_valid_ident, quote_ident},
+    schema::{
+        column_sql, create_table_sql, default_sql, diff_columns, is_literal, table_columns,
+        table_indexes, TableColumn,
+    },
+    Column, Error, IfNotExists, SqliteModel,
+};
+
+/// The table recording which migrations have been applied
+pub const MIGRATIONS_TABLE: &str = "_sqlx_model_migrations";
+
+/// One versioned change to the schema, with the script that applies it and optionally the script
+/// that reverts it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub version: i64,
+    pub name: Cow<'static, str>,
+    pub up: Cow<'static, str>,
+    pub down: Option<Cow<'static, str>>,
+}
+
+impl Migration {
+    /// A migration embedded in the binary, eg with `include_str!`
+    pub const fn new(version: i64, name: &'static str, up: &'static str) -> Self {
+        Migration {
+            version,
+            name: Cow::Borrowed(name),
+            up: Cow::Borrowed(up),
+            down: None,
+        }
+    }
+
+    pub fn down(mut self, down: &'static str) -> Self {
+        self.down = Some(Cow::Borrowed(down));
+        self
+    }
+
+    /// The hex encoded SHA-256 of the `up` script, recorded when the migration is applied
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+/// Applies and rolls back an ordered set of migrations, recording every applied version and its
+/// checksum in [`MIGRATIONS_TABLE`]
+///
+/// # Examples
+/// ```
+/// use sqlx_model::{Migration, Migrator};
+///
+/// let migrator = Migrator::new(vec![
+///     Migration::new(1, "create_users", "create table users (id integer primary key);")
+///         .down("drop table users;"),
+///     Migration::new(2, "add_email", "alter table users add column email text;")
+///         .down("alter table users drop column email;"),
+/// ])
+/// .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migrator {
+    migrations: Vec<Migration>,
+}
+
+impl Migrator {
+    /// Order `migrations` by version
+    ///
+    /// # Errors
+    /// Returns `Error::Migration` if two migrations share a version
+    pub fn new(mut migrations: Vec<Migration>) -> Result<Self, Error> {
+        migrations.sort_by_key(|m| m.version);
+        if let Some(pair) = migrations.windows(2).find(|w| w[0].version == w[1].version) {
+            return Err(Error::Migration(format!(
+                "migrations {} and {} share version {}",
+                pair[0].name, pair[1].name, pair[0].version
+            )));
+        }
+        Ok(Migrator { migrations })
+    }
+
+    /// Read the migrations in `dir`. Every migration is a `<version>_<name>.up.sql` file, with an
+    /// optional `<version>_<name>.down.sql` file next to it. Other files are ignored
+    ///
+    /// # Errors
+    /// Returns `Error::Migration` if `dir` cannot be read, a file name has no numeric version, a
+    /// down script has no matching up script, or two migrations share a version
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let read_err = |e: std::io::Error| Error::Migration(format!("{}: {}", dir.display(), e));
+        let mut ups = Vec::new();
+        let mut downs = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(read_err)? {
+            let path = entry.map_err(read_err)?.path();
+            let file_name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            let (stem, is_up) = match (
+                file_name.strip_suffix(".up.sql"),
+                file_name.strip_suffix(".down.sql"),
+            ) {
+                (Some(stem), _) => (stem, true),
+                (_, Some(stem)) => (stem, false),
+                _ => continue,
+            };
+            let (version, name) = stem
+                .split_once('_')
+                .and_then(|(v, n)| Some((v.parse::<i64>().ok()?, n.to_string())))
+                .ok_or_else(|| {
+                    Error::Migration(format!(
+                        "{}: expected a file named <version>_<name>.up.sql or .down.sql",
+                        path.display()
+                    ))
+                })?;
+            let script = std::fs::read_to_string(&path)
+                .map_err(|e| Error::Migration(format!("{}: {}", path.display(), e)))?;
+            match is_up {
+                true => ups.push((version, name, script)),
+                false => downs.push((version, name, script)),
+            }
+        }
+
+        let mut migrations: Vec<Migration> = ups
+            .into_iter()
+            .map(|(version, name, up)| Migration {
+                version,
+                name: Cow::Owned(name),
+                up: Cow::Owned(up),
+                down: None,
+            })
+            .collect();
+        for (version, name, down) in downs {
+            let migration = migrations
+                .iter_mut()
+                .find(|m| m.version == version && m.name == name)
+                .ok_or_else(|| {
+                    Error::Migration(format!(
+                        "{}_{}.down.sql has no matching up script",
+                        version, name
+                    ))
+                })?;
+            migration.down = Some(Cow::Owned(down));
+        }
+        Migrator::new(migrations)
+    }
+
+    pub fn migrations(&self) -> &[Migration] {
+        &self.migrations
+    }
+
+    /// Apply every migration that has not been applied yet, in order of version. Each migration
+    /// runs in its own transaction along with the record of it being applied, so a failing
+    /// migration leaves the ones before it applied and nothing of itself behind
+    ///
+    /// # Arguments
+    /// - conn: The sqlx::SqlitePool or connection to migrate.
+    ///
+    /// # Returns
+    /// - Result<Vec<i64>, Error>: The versions applied by this call, in the order they ran.
+    ///
+    /// # Errors
+    /// Returns `Error::ChecksumMismatch` without applying anything if an applied migration has
+    /// changed since it ran, `Error::Migration` if an applied version is not part of this set,
+    /// and `Error::Database` if a script fails
+    pub async fn run<'a, A>(&self, conn: A) -> Result<Vec<i64>, Error>
+    where
+        A: Acquire<'a, Database = sqlx::Sqlite> + Send,
+    {
+        let mut conn = conn.acquire().await?;
+        let applied = self.verify(&mut conn).await?;
+
+        let mut ran = Vec::new();
+        for migration in &self.migrations {
+            if applied.contains(&migration.version) {
+                continue;
+            }
+            let mut tx = Connection::begin(&mut *conn).await?;
+            sqlx::raw_sql(&migration.up).execute(&mut *tx).await?;
+            sqlx::query(&format!(
+                "insert into \"{}\" (version, name, checksum) values (?, ?, ?);",
+                MIGRATIONS_TABLE
+            ))
+            .bind(migration.version)
+            .bind(migration.name.as_ref())
+            .bind(migration.checksum())
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            ran.push(migration.version);
+        }
+        Ok(ran)
+    }
+
+    /// Revert every applied migration with a version greater than `target`, newest first. Each
+    /// migration is reverted in its own transaction
+    ///
+    /// # Arguments
+    /// - conn: The sqlx::SqlitePool or connection to migrate.
+    /// - target: The version to roll back to. `0` reverts every migration.
+    ///
+    /// # Returns
+    /// - Result<Vec<i64>, Error>: The versions reverted by this call, in the order they ran.
+    ///
+    /// # Errors
+    /// Returns `Error::ChecksumMismatch` without reverting anything if an applied migration has
+    /// changed since it ran, `Error::Migration` if a migration to revert has no down script, and
+    /// `Error::Database` if a script fails
+    pub async fn rollback<'a, A>(&self, conn: A, target: i64) -> Result<Vec<i64>, Error>
+    where
+        A: Acquire<'a, Database = sqlx::Sqlite> + Send,
+    {
+        let mut conn = conn.acquire().await?;
+        let applied = self.verify(&mut conn).await?;
+        let to_revert: Vec<&Migration> = self
+            .migrations
+            .iter()
+            .rev()
+            .filter(|m| m.version > target && applied.contains(&m.version))
+            .collect();
+        if let Some(m) = to_revert.iter().find(|m| m.down.is_none()) {
+            return Err(Error::Migration(format!(
+                "migration {} ({}) has no down script",
+                m.version, m.name
+            )));
+        }
+
+        let mut reverted = Vec::new();
+        for migration in to_revert {
+            let mut tx = Connection::begin(&mut *conn).await?;
+            let down = migration.down.as_deref().unwrap_or_default();
+            sqlx::raw_sql(down).execute(&mut *tx).await?;
+            sqlx::query(&format!(
+                "delete from \"{}\" where version = ?;",
+                MIGRATIONS_TABLE
+            ))
+            .bind(migration.version)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            reverted.push(migration.version);
+        }
+        Ok(reverted)
+    }
+
+    /// The versions recorded as applied, in ascending order
+    pub async fn applied<'a, A>(&self, conn: A) -> Result<Vec<i64>, Error>
+    where
+        A: Acquire<'a, Database = sqlx::Sqlite> + Send,
+    {
+        let mut conn = conn.acquire().await?;
+        Ok(applied_migrations(&mut conn)
+            .await?
+            .into_iter()
+            .map(|(version, _, _)| version)
+            .collect())
+    }
+
+    /// Check every applied migration against this set, returning the applied versions
+    async fn verify(&self, conn: &mut sqlx::SqliteConnection) -> Result<Vec<i64>, Error> {
+        let applied = applied_migrations(conn).await?;
+        for (version, name, checksum) in &applied {
+            let migration = self
+                .migrations
+                .iter()
+                .find(|m| m.version == *version)
+                .ok_or_else(|| {
+                    Error::Migration(format!(
+                        "applied migration {} ({}) is missing",
+                        version, name
+                    ))
+                })?;
+            if migration.checksum() != *checksum {
+                return Err(Error::ChecksumMismatch {
+                    version: *version,
+                    name: name.clone(),
+                });
+            }
+        }
+        Ok(applied.into_iter().map(|(version, _, _)| version).collect())
+    }
+}
+
+/// Create [`MIGRATIONS_TABLE`] if needed and read the version, name and checksum of every
+/// applied migration
+async fn applied_migrations(
+    conn: &mut sqlx::SqliteConnection,
+) -> Result<Vec<(i64, String, String)>, Error> {
+    sqlx::query(&format!(
+        r#"create table if not exists "{}" (
+            version integer primary key,
+            name text not null,
+            checksum text not null,
+            applied_at integer not null default (unixepoch())
+        );"#,
+        MIGRATIONS_TABLE
+    ))
+    .execute(&mut *conn)
+    .await?;
+    let applied = sqlx::query_as(&format!(
+        "select version, name, checksum from \"{}\" order by version;",
+        MIGRATIONS_TABLE
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(applied)
+}
+
+/// Compare the columns `M` declares with its live table and build the migration that brings the
+/// table in line with the model, or `None` if they already match
+///
+/// New columns are added with `alter table ... add column` when SQLite allows it: the column is
+/// not a primary key or unique, and any default is a literal, which a `not null` column must
+/// have. Every other change rebuilds the table: a new table is created, the columns both sides
+/// share are copied into it, and it replaces the old one, whose indexes are recreated if their
+/// columns still exist. The down script reverses the change the same way, recreating the old
+/// table from its `PRAGMA table_info`, so `check` and `foreign key` constraints of the old table
+/// are not restored. Triggers and views on the table are left untouched
+///
+/// # Arguments
+/// - conn: The sqlx::SqlitePool or connection to inspect.
+/// - version: The version of the migration.
+/// - name: The name of the migration, which must be a valid identifier.
+///
+/// # Errors
+/// Returns `Error::InvalidQuery` if `M` does not declare its `columns`, `Error::Migration` if
+/// `name` is not a valid identifier, and `Error::Database` if the table cannot be inspected
+pub async fn diff_migration<'a, M>(
+    conn: impl Acquire<'a, Database = sqlx::Sqlite> + Send,
+    version: i64,
+    name: &str,
+) -> Result<Option<Migration>, Error>
+where
+    M: SqliteModel + ?Sized,
+{
+    if !is_valid_ident(name) {
+        return Err(Error::Migration(format!(
+            "{:?} is not a valid migration name",
+            name
+        )));
+    }
+    let table = M::table_name();
+    let declared = M::columns();
+    let create = create_table_sql(&table, declared, IfNotExists(false))?;
+    let mut conn = conn.acquire().await?;
+    let found = table_columns(&mut conn, &table).await?;
+    let quoted_table = quote_ident(&table)?;
+
+    let (up, down) = if found.is_empty() {
+        (create, format!("drop table {};", quoted_table))
+    } else {
+        let diff = diff_columns(declared, &found);
+        if diff.is_empty() {
+            return Ok(None);
+        }
+        let added: Vec<&Column> = declared
+            .iter()
+            .filter(|c| diff.missing_columns.iter().any(|m| m == c.name))
+            .collect();
+        if diff.extra_columns.is_empty()
+            && diff.mismatches.is_empty()
+            && added.iter().all(|c| can_add_column(c))
+        {
+            let mut up = Vec::new();
+            let mut down = Vec::new();
+            for column in added {
+                let def = column_sql(column, declared)?;
+                up.push(format!("alter table {} add column {};", quoted_table, def));
+                down.insert(
+                    0,
+                    format!(
+                        "alter table {} drop column {};",
+                        quoted_table,
+                        quote_ident(column.name)?
+                    ),
+                );
+            }
+            (up.join("\n"), down.join("\n"))
+        } else {
+            let indexes = table_indexes(&mut conn, &table).await?;
+            let new_defs = declared
+                .iter()
+                .map(|c| column_sql(c, declared))
+                .collect::<Result<Vec<_>, Error>>()?;
+            let new_names: Vec<&str> = declared.iter().map(|c| c.name).collect();
+            let old_names: Vec<&str> = found.iter().map(|c| c.name.as_str()).collect();
+            let up = rebuild_table_sql(&table, &new_defs, &old_names, &new_names, &indexes)?;
+            let down = rebuild_table_sql(
+                &table,
+                &table_column_defs(&found)?,
+                &new_names,
+                &old_names,
+                &indexes,
+            )?;
+            (up, down)
+        }
+    };
+    Ok(Some(Migration {
+        version,
+        name: Cow::Owned(name.to_string()),
+        up: Cow::Owned(up),
+        down: Some(Cow::Owned(down)),
+    }))
+}
+
+/// Build the migration of [`diff_migration`] and write it to `dir` as
+/// `<version>_<name>.up.sql` and `<version>_<name>.down.sql`, numbered one past the highest
+/// version already in `dir`. Nothing is written if the table already matches the model
+///
+/// # Arguments
+/// - conn: The sqlx::SqlitePool or connection to inspect.
+/// - dir: The directory of migrations, which is created if it does not exist.
+/// - name: The name of the migration, which must be a valid identifier.
+///
+/// # Returns
+/// - Result<Option<PathBuf>, Error>: The path of the new up script, or `None` if there was
+///   nothing to migrate.
+///
+/// # Errors
+/// Returns the errors of [`diff_migration`] and [`Migrator::from_dir`], or `Error::Migration`
+/// if the scripts cannot be written
+pub async fn generate_migration<'a, M>(
+    conn: impl Acquire<'a, Database = sqlx::Sqlite> + Send,
+    dir: impl AsRef<Path>,
+    name: &str,
+) -> Result<Option<PathBuf>, Error>
+where
+    M: SqliteModel + ?Sized,
+{
+    let dir = dir.as_ref();
+    let write_err =
+        |path: &Path, e: std::io::Error| Error::Migration(format!("{}: {}", path.display(), e));
+    std::fs::create_dir_all(dir).map_err(|e| write_err(dir, e))?;
+    let version = Migrator::from_dir(dir)?
+        .migrations
+        .last()
+        .map_or(1, |m| m.version + 1);
+    let Some(migration) = diff_migration::<M>(conn, version, name).await? else {
+        return Ok(None);
+    };
+
+    let up_path = dir.join(format!("{}_{}.up.sql", version, name));
+    let down_path = dir.join(format!("{}_{}.down.sql", version, name));
+    std::fs::write(&up_path, migration.up.as_bytes()).map_err(|e| write_err(&up_path, e))?;
+    let down = migration.down.unwrap_or_default();
+    std::fs::write(&down_path, down.as_bytes()).map_err(|e| write_err(&down_path, e))?;
+    Ok(Some(up_path))
+}
+
+/// Whether SQLite can add `column` to an existing table with `alter table ... add column`
+fn can_add_column(column: &Column) -> bool {
+    let literal_default = column.default.filter(|d| is_literal(d));
+    let has_value = literal_default.is_some_and(|d| !d.eq_ignore_ascii_case("null"));
+    !column.primary_key
+        && !column.unique
+        && column.default == literal_default
+        && (!column.not_null || has_value)
+}
+
+/// The column definitions that recreate the `columns` of a live table
+fn table_column_defs(columns: &[TableColumn]) -> Result<Vec<String>, Error> {
+    let composite_key = columns.iter().filter(|c| c.primary_key).count() > 1;
+    let mut defs = Vec::with_capacity(columns.len() + 1);
+    for column in columns {
+        let mut def = quote_ident(&column.name)?;
+        if !column.declared_type.is_empty() {
+            def.push_str(&format!(" {}", column.declared_type));
+        }
+        if column.primary_key && !composite_key {
+            def.push_str(" primary key");
+        }
+        if column.not_null {
+            def.push_str(" not null");
+        }
+        if column.unique {
+            def.push_str(" unique");
+        }
+        if let Some(default) = &column.default {
+            def.push_str(&format!(" default {}", default_sql(default)));
+        }
+        defs.push(def);
+    }
+    if composite_key {
+        let keys = columns
+            .iter()
+            .filter(|c| c.primary_key)
+            .map(|c| quote_ident(&c.name))
+            .collect::<Result<Vec<_>, Error>>()?;
+        defs.push(format!("primary key ({})", keys.join(",")));
+    }
+    Ok(defs)
+}
+
+/// The statements replacing `table` with a table of `defs`, copying the columns present in both
+/// `from_columns` and `to_columns`, and recreating the `indexes` whose columns all survive
+fn rebuild_table_sql(
+    table: &str,
+    defs: &[String],
+    from_columns: &[&str],
+    to_columns: &[&str],
+    indexes: &[(String, Vec<String>)],
+) -> Result<String, Error> {
+    let quoted_table = quote_ident(table)?;
+    let new_table = quote_ident(&format!("_sqlx_model_new_{}", table))?;
+    let shared = to_columns
+        .iter()
+        .filter(|c| from_columns.contains(c))
+        .map(|c| quote_ident(c))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let mut statements = vec![
+        "pragma defer_foreign_keys = on;".to_string(),
+        format!(
+            "create table {} (\n    {}\n);",
+            new_table,
+            defs.join(",\n    ")
+        ),
+    ];
+    // With no column in common there is nothing to copy, and `insert into t () ...` is invalid
+    if !shared.is_empty() {
+        let shared = shared.join(",");
+        statements.push(format!(
+            "insert into {} ({}) select {} from {};",
+            new_table, shared, shared, quoted_table
+        ));
+    }
+    statements.push(format!("drop table {};", quoted_table));
+    statements.push(format!(
+        "alter table {} rename to {};",
+        new_table, quoted_table
+    ));
+    for (sql, columns) in indexes {
+        if columns.iter().all(|c| to_columns.contains(&c.as_str())) {
+            statements.push(format!("{};", sql));
+        }
+    }
+    Ok(statements.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::{diff_migration, generate_migration, Migration, Migrator};
+    use crate::{verify_schema, Error};
+
+    fn migrations() -> Vec<Migration> {
+        vec![
+            Migration::new(2, "add_email", "alter table users add column email text;")
+                .down("alter table users drop column email;"),
+            Migration::new(
+                1,
+                "create_users",
+                "create table users (id integer primary key, name text not null);",
+            )
+            .down("drop table users;"),
+        ]
+    }
+
+    async fn tables(pool: &sqlx::SqlitePool) -> Vec<String> {
+        sqlx::query_scalar("select name from sqlite_master where type = 'table' order by name")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_run_and_rollback() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        let migrator = Migrator::new(migrations()).unwrap();
+        assert_eq!(migrator.run(&pool).await.unwrap(), vec![1, 2]);
+        assert_eq!(migrator.run(&pool).await.unwrap(), Vec::<i64>::new());
+        assert_eq!(migrator.applied(&pool).await.unwrap(), vec![1, 2]);
+        sqlx::query("insert into users (name, email) values ('alice', 'a@example.com')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(migrator.rollback(&pool, 1).await.unwrap(), vec![2]);
+        assert!(sqlx::query("select email from users")
+            .execute(&pool)
+            .await
+            .is_err());
+        assert_eq!(migrator.rollback(&pool, 0).await.unwrap(), vec![1]);
+        assert_eq!(tables(&pool).await, vec!["_sqlx_model_migrations"]);
+        assert_eq!(migrator.applied(&pool).await.unwrap(), Vec::<i64>::new());
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_rolls_back() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        let mut set = migrations();
+        set.push(Migration::new(
+            3,
+            "broken",
+            "create table posts (id integer primary key); insert into missing values (1);",
+        ));
+        let migrator = Migrator::new(set).unwrap();
+        assert!(matches!(migrator.run(&pool).await, Err(Error::Database(_))));
+        assert_eq!(migrator.applied(&pool).await.unwrap(), vec![1, 2]);
+        assert!(!tables(&pool).await.contains(&"posts".to_string()));
+        assert!(matches!(
+            migrator.rollback(&pool, 0).await,
+            Ok(reverted) if reverted == vec![2, 1]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_checksum_mismatch() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        Migrator::new(migrations())
+            .unwrap()
+            .run(&pool)
+            .await
+            .unwrap();
+
+        let mut changed = migrations();
+        changed[1].up = "create table users (id integer primary key);".into();
+        changed.push(Migration::new(
+            3,
+            "posts",
+            "create table posts (id integer);",
+        ));
+        let migrator = Migrator::new(changed).unwrap();
+        match migrator.run(&pool).await {
+            Err(Error::ChecksumMismatch { version, name }) => {
+                assert_eq!(version, 1);
+                assert_eq!(name, "create_users");
+            }
+            res => panic!("expected a checksum mismatch, found {:?}", res),
+        }
+        assert!(migrator.rollback(&pool, 0).await.is_err());
+        assert!(!tables(&pool).await.contains(&"posts".to_string()));
+
+        let migrator = Migrator::new(migrations()[1..].to_vec()).unwrap();
+        assert!(matches!(
+            migrator.run(&pool).await,
+            Err(Error::Migration(_))
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_versions() {
+        let mut set = migrations();
+        set.push(Migration::new(2, "again", ""));
+        assert!(matches!(Migrator::new(set), Err(Error::Migration(_))));
+    }
+
+    #[tokio::test]
+    async fn test_from_dir() {
+        let dir =
+            std::env::temp_dir().join(format!("sqlx_model_migrations_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, script) in [
+            (
+                "1_create_users.up.sql",
+                "create table users (id integer primary key);",
+            ),
+            ("1_create_users.down.sql", "drop table users;"),
+            (
+                "10_create_posts.up.sql",
+                "create table posts (id integer primary key);",
+            ),
+            (
+                "2_add_name.up.sql",
+                "alter table users add column name text;",
+            ),
+            ("README.md", "not a migration"),
+        ] {
+            std::fs::write(dir.join(file), script).unwrap();
+        }
+        let migrator = Migrator::from_dir(&dir);
+
+        std::fs::write(dir.join("3_orphan.down.sql"), "").unwrap();
+        let orphan = Migrator::from_dir(&dir);
+        std::fs::write(dir.join("latest.up.sql"), "").unwrap();
+        let unversioned = Migrator::from_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let migrator = migrator.unwrap();
+        let versions: Vec<_> = migrator
+            .migrations()
+            .iter()
+            .map(|m| (m.version, m.name.as_ref(), m.down.is_some()))
+            .collect();
+        assert_eq!(
+            versions,
+            vec![
+                (1, "create_users", true),
+                (2, "add_name", false),
+                (10, "create_posts", false)
+            ]
+        );
+        assert!(matches!(orphan, Err(Error::Migration(_))));
+        assert!(matches!(unversioned, Err(Error::Migration(_))));
+
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        assert_eq!(migrator.run(&pool).await.unwrap(), vec![1, 2, 10]);
+        assert!(matches!(
+            migrator.rollback(&pool, 1).await,
+            Err(Error::Migration(_))
+        ));
+        assert_eq!(migrator.applied(&pool).await.unwrap(), vec![1, 2, 10]);
+    }
+
+    #[derive(Debug, Serialize, crate::SqliteModel)]
+    #[model(table = "items")]
+    struct ItemV1 {
+        #[model(primary_key)]
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Debug, Serialize, crate::SqliteModel)]
+    #[model(table = "items")]
+    struct ItemV2 {
+        #[model(primary_key)]
+        id: i64,
+        name: String,
+        note: Option<String>,
+        #[model(default = "'new'")]
+        status: String,
+    }
+
+    #[derive(Debug, Serialize, crate::SqliteModel)]
+    #[model(table = "items")]
+    struct ItemV3 {
+        #[model(primary_key)]
+        id: i64,
+        #[model(unique)]
+        name: String,
+        #[model(default = "'new'")]
+        status: String,
+    }
+
+    #[tokio::test]
+    async fn test_generate_migration() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        let dir = std::env::temp_dir().join(format!("sqlx_model_generate_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let path = generate_migration::<ItemV1>(&pool, &dir, "create_items")
+            .await
+            .unwrap();
+        assert_eq!(path, Some(dir.join("1_create_items.up.sql")));
+        Migrator::from_dir(&dir).unwrap().run(&pool).await.unwrap();
+        assert!(verify_schema::<ItemV1>(&pool).await.unwrap().is_empty());
+        assert!(generate_migration::<ItemV1>(&pool, &dir, "noop")
+            .await
+            .unwrap()
+            .is_none());
+        sqlx::query(
+            "insert into items (name) values ('widget'); create index items_name on items (name);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let added = diff_migration::<ItemV2>(&pool, 2, "add_status")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            added.up,
+            "alter table \"items\" add column \"note\" text;\n\
+            alter table \"items\" add column \"status\" text not null default 'new';"
+        );
+        generate_migration::<ItemV2>(&pool, &dir, "add_status")
+            .await
+            .unwrap();
+        let migrator = Migrator::from_dir(&dir).unwrap();
+        assert_eq!(migrator.run(&pool).await.unwrap(), vec![2]);
+        assert!(verify_schema::<ItemV2>(&pool).await.unwrap().is_empty());
+
+        let rebuilt = diff_migration::<ItemV3>(&pool, 3, "rebuild_items")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(rebuilt
+            .up
+            .contains("create table \"_sqlx_model_new_items\""));
+        generate_migration::<ItemV3>(&pool, &dir, "rebuild_items")
+            .await
+            .unwrap();
+        let migrator = Migrator::from_dir(&dir).unwrap();
+        assert_eq!(migrator.run(&pool).await.unwrap(), vec![3]);
+        assert!(verify_schema::<ItemV3>(&pool).await.unwrap().is_empty());
+        let row: (String, String) = sqlx::query_as("select name, status from items")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(row, ("widget".to_string(), "new".to_string()));
+        let index: Option<String> =
+            sqlx::query_scalar("select name from sqlite_master where name = 'items_name'")
+                .fetch_optional(&pool)
+                .await
+                .unwrap();
+        assert!(index.is_some());
+
+        assert_eq!(migrator.rollback(&pool, 1).await.unwrap(), vec![3, 2]);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(verify_schema::<ItemV1>(&pool).await.unwrap().is_empty());
+        let name: String = sqlx::query_scalar("select name from items")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(name, "widget");
+
+        assert!(matches!(
+            diff_migration::<ItemV2>(&pool, 2, "add status").await,
+            Err(Error::Migration(_))
+        ));
+    }
+
+    #[derive(Debug, Serialize, crate::SqliteModel)]
+    #[model(table = "gadgets")]
+    struct Gadget {
+        #[model(primary_key)]
+        id: i64,
+        label: String,
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_disjoint_columns() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::query("create table gadgets (code text, weight real);")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let rebuilt = diff_migration::<Gadget>(&pool, 1, "replace_gadgets")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!rebuilt.up.contains("insert into"));
+        assert!(!rebuilt.down.as_deref().unwrap().contains("insert into"));
+
+        let migrator = Migrator::new(vec![rebuilt]).unwrap();
+        migrator.run(&pool).await.unwrap();
+        assert!(verify_schema::<Gadget>(&pool).await.unwrap().is_empty());
+        migrator.rollback(&pool, 0).await.unwrap();
+        let columns: Vec<String> =
+            sqlx::query_scalar("select name from pragma_table_info('gadgets') order by cid")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(columns, ["code", "weight"]);
+    }
+}
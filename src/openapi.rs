@@ -0,0 +1,453 @@
+use std::sync::Arc;
+
+use axum::{routing::get, Json, Router};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::{router::key_column, Affinity, Column, CrudRoute, Error, SqliteModel};
+
+/// The name of the component schema shared by every error response
+const PROBLEM_SCHEMA: &str = "Problem";
+
+/// An OpenAPI 3.1 document describing `SqliteModel` types and the `crud_router` routes which
+/// expose them
+///
+/// # Examples
+/// ```ignore
+/// let users = crud_router::<User>().without(CrudRoute::Delete);
+/// let api = OpenApi::new("Users", "1.0.0").resource::<User>("/users", &users.routes());
+/// let app: Router = Router::new()
+///     .nest("/users", users.into_router())
+///     .merge(api.into_router())
+///     .with_state(pool);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OpenApi(Value);
+
+impl OpenApi {
+    pub fn new(title: &str, version: &str) -> Self {
+        OpenApi(json!({
+            "openapi": "3.1.0",
+            "info": { "title": title, "version": version },
+            "paths": {},
+            "components": {
+                "schemas": {
+                    PROBLEM_SCHEMA: {
+                        "type": "object",
+                        "properties": {
+                            "type": { "type": "string" },
+                            "title": { "type": "string" },
+                            "status": { "type": "integer" },
+                            "detail": { "type": "string" },
+                        },
+                        "required": ["type", "title", "status"],
+                    }
+                }
+            },
+        }))
+    }
+
+    /// The document as JSON
+    pub fn document(&self) -> &Value {
+        &self.0
+    }
+
+    /// Add the component schema of `M`, named after the type
+    pub fn schema<M: SqliteModel>(mut self) -> Self {
+        self.0["components"]["schemas"][schema_name::<M>()] = model_schema::<M>();
+        self
+    }
+
+    /// Add the schema of `M` and an operation for each of `routes`, as mounted by
+    /// `crud_router::<M>()` nested at `path`
+    pub fn resource<M: SqliteModel>(mut self, path: &str, routes: &[CrudRoute]) -> Self {
+        self = self.schema::<M>();
+        let path = path.trim_end_matches('/');
+        let item_path = format!("{}/{{id}}", path);
+        let root_path = match path {
+            "" => "/",
+            path => path,
+        };
+        for route in routes {
+            let (path, method) = match route {
+                CrudRoute::List => (root_path, "get"),
+                CrudRoute::Create => (root_path, "post"),
+                CrudRoute::Get => (item_path.as_str(), "get"),
+                CrudRoute::Replace => (item_path.as_str(), "put"),
+                CrudRoute::Update => (item_path.as_str(), "patch"),
+                CrudRoute::Delete => (item_path.as_str(), "delete"),
+            };
+            self.0["paths"][path][method] = operation::<M>(*route);
+        }
+        self
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(&self.0)?)
+    }
+
+    /// # Errors
+    /// Returns `Error::Serialization` if the document cannot be represented as YAML
+    pub fn to_yaml(&self) -> Result<String, Error> {
+        serde_yaml::to_string(&self.0)
+            .map_err(|e| Error::Serialization(serde::ser::Error::custom(e)))
+    }
+
+    /// A router serving the document at `GET /openapi.json`
+    pub fn into_router<S>(self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let document = Arc::new(self.0);
+        Router::new().route(
+            "/openapi.json",
+            get(move || {
+                let document = Arc::clone(&document);
+                async move { Json(document) }
+            }),
+        )
+    }
+}
+
+/// The name of the component schema of `M`, the name of the type without its module path
+fn schema_name<M: SqliteModel>() -> &'static str {
+    let full_path = std::any::type_name::<M>();
+    full_path.rsplit("::").next().unwrap_or(full_path)
+}
+
+fn schema_ref<M: SqliteModel>() -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", schema_name::<M>()) })
+}
+
+/// The schema of the value of `column` as it is serialized. JSON columns may hold anything
+fn column_schema(column: &Column) -> Value {
+    let mut schema = match (column.json, column.affinity) {
+        (true, _) => return json!({}),
+        (false, _) if column.boolean => json!({ "type": "boolean" }),
+        (false, None) => return json!({}),
+        (false, Some(Affinity::Integer)) => json!({ "type": "integer" }),
+        (false, Some(Affinity::Real | Affinity::Numeric)) => json!({ "type": "number" }),
+        (false, Some(Affinity::Text)) => json!({ "type": "string" }),
+        (false, Some(Affinity::Blob)) => json!({
+            "type": "array",
+            "items": { "type": "integer", "minimum": 0, "maximum": 255 },
+        }),
+    };
+    if !column.not_null {
+        schema["type"] = json!([schema["type"], "null"]);
+    }
+    schema
+}
+
+/// The object schema of `M`, with a property for each of its columns named after the field it
+/// maps to as it is serialized. Models which do not declare their columns are described as any object
+fn model_schema<M: SqliteModel>() -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for column in M::columns() {
+        properties.insert(column.field.to_string(), column_schema(column));
+        if column.not_null {
+            required.push(column.field);
+        }
+    }
+    let mut schema = json!({ "type": "object" });
+    if !properties.is_empty() {
+        schema["properties"] = Value::Object(properties);
+        schema["required"] = json!(required);
+    }
+    schema
+}
+
+fn problem_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": {
+            "application/problem+json": {
+                "schema": { "$ref": format!("#/components/schemas/{}", PROBLEM_SCHEMA) },
+            },
+        },
+    })
+}
+
+fn json_content(schema: Value) -> Value {
+    json!({ "application/json": { "schema": schema } })
+}
+
+/// The query parameters accepted by the list route, see `ModelQuery`
+fn list_parameters<M: SqliteModel>() -> Vec<Value> {
+    let mut parameters: Vec<Value> = M::columns()
+        .iter()
+        .map(|column| {
+            json!({
+                "name": column.field,
+                "in": "query",
+                "required": false,
+                "schema": column_schema(column),
+            })
+        })
+        .collect();
+    let max_limit = M::max_limit().unwrap_or(i64::MAX as u64);
+    for (name, schema) in [
+        ("sort", json!({ "type": "string" })),
+        (
+            "limit",
+            json!({ "type": "integer", "minimum": 0, "maximum": max_limit }),
+        ),
+        (
+            "offset",
+            json!({ "type": "integer", "minimum": 0, "maximum": i64::MAX }),
+        ),
+    ] {
+        parameters
+            .push(json!({ "name": name, "in": "query", "required": false, "schema": schema }));
+    }
+    parameters
+}
+
+/// The key of a record in the path of the item routes
+fn key_parameter<M: SqliteModel>() -> Value {
+    let schema = match M::columns().iter().find(|c| c.primary_key) {
+        Some(column) => column_schema(&Column {
+            not_null: true,
+            ..*column
+        }),
+        None => json!({ "type": ["integer", "string"] }),
+    };
+    json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "description": format!("The {} of the record", key_column::<M>()),
+        "schema": schema,
+    })
+}
+
+fn operation<M: SqliteModel>(route: CrudRoute) -> Value {
+    let record = |description: &str| json!({ "description": description, "content": json_content(schema_ref::<M>()) });
+    let not_found = || ("404", problem_response("No record has the given key"));
+    let conflict = || {
+        (
+            "409",
+            problem_response("A unique or foreign key constraint failed"),
+        )
+    };
+    let invalid_body = || ("400", problem_response("The request body is invalid"));
+    let unsupported = || {
+        (
+            "422",
+            problem_response("A value fails validation or cannot be stored"),
+        )
+    };
+    let (id, summary, body, mut responses) = match route {
+        CrudRoute::List => {
+            let records = json!({ "type": "array", "items": schema_ref::<M>() });
+            let records =
+                json!({ "description": "The matching records", "content": json_content(records) });
+            let invalid = problem_response("A query parameter is invalid");
+            (
+                "list",
+                "List the records of",
+                None,
+                vec![("200", records), ("400", invalid)],
+            )
+        }
+        CrudRoute::Get => (
+            "get",
+            "Get a record of",
+            None,
+            vec![("200", record("The record")), not_found()],
+        ),
+        CrudRoute::Create => (
+            "create",
+            "Create a record of",
+            Some(schema_ref::<M>()),
+            vec![
+                ("201", record("The created record")),
+                invalid_body(),
+                conflict(),
+                unsupported(),
+            ],
+        ),
+        CrudRoute::Replace => (
+            "replace",
+            "Create or replace a record of",
+            Some(schema_ref::<M>()),
+            vec![
+                ("200", record("The saved record")),
+                invalid_body(),
+                conflict(),
+                unsupported(),
+            ],
+        ),
+        CrudRoute::Update => {
+            // Any subset of the fields may be given, so none of them are required
+            let mut partial = model_schema::<M>();
+            if let Some(partial) = partial.as_object_mut() {
+                partial.remove("required");
+            }
+            let responses = vec![
+                ("200", record("The saved record")),
+                invalid_body(),
+                not_found(),
+                conflict(),
+                unsupported(),
+            ];
+            (
+                "update",
+                "Update fields of a record of",
+                Some(partial),
+                responses,
+            )
+        }
+        CrudRoute::Delete => {
+            let deleted = json!({ "description": "The record was deleted" });
+            (
+                "delete",
+                "Delete a record of",
+                None,
+                vec![("204", deleted), not_found()],
+            )
+        }
+    };
+    responses.push(("500", problem_response("The database operation failed")));
+    let parameters = match route {
+        CrudRoute::List => list_parameters::<M>(),
+        CrudRoute::Create => Vec::new(),
+        _ => vec![key_parameter::<M>()],
+    };
+
+    let table = M::table_name();
+    let responses: Map<String, Value> = responses
+        .into_iter()
+        .map(|(status, response)| (status.to_string(), response))
+        .collect();
+    let mut operation = json!({
+        "operationId": format!("{}_{}", id, table),
+        "summary": format!("{} {}", summary, table),
+        "tags": [table],
+        "parameters": parameters,
+        "responses": responses,
+    });
+    if let Some(body) = body {
+        operation["requestBody"] = json!({ "required": true, "content": json_content(body) });
+    }
+    operation
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use super::OpenApi;
+    use crate::{crud_router, CrudRoute};
+
+    #[derive(Debug, Serialize, Deserialize, crate::SqliteModel)]
+    #[model(table = "users")]
+    struct User {
+        #[model(primary_key, skip_insert)]
+        id: i64,
+        #[model(rename = "user_name")]
+        name: String,
+        email: Option<String>,
+        #[model(json)]
+        tags: Vec<String>,
+        #[serde(rename = "active")]
+        is_active: bool,
+        verified: Option<bool>,
+    }
+
+    #[test]
+    fn test_schema() {
+        let api = OpenApi::new("Users", "1.0.0").schema::<User>();
+        assert_eq!(api.document()["openapi"], "3.1.0");
+        let problem = &api.document()["components"]["schemas"]["Problem"];
+        assert_eq!(problem["required"], json!(["type", "title", "status"]));
+        assert_eq!(
+            api.document()["components"]["schemas"]["User"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "id": { "type": "integer" },
+                    "name": { "type": "string" },
+                    "email": { "type": ["string", "null"] },
+                    "tags": {},
+                    "active": { "type": "boolean" },
+                    "verified": { "type": ["boolean", "null"] },
+                },
+                "required": ["id", "name", "tags", "active"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_resource() {
+        let users = crud_router::<User>().without(CrudRoute::Delete);
+        let api = OpenApi::new("Users", "1.0.0").resource::<User>("/users/", &users.routes());
+        let paths = &api.document()["paths"];
+        let methods = |path: &str| {
+            let mut methods: Vec<&String> = paths[path].as_object().unwrap().keys().collect();
+            methods.sort();
+            methods
+        };
+        assert_eq!(methods("/users"), ["get", "post"]);
+        assert_eq!(methods("/users/{id}"), ["get", "patch", "put"]);
+
+        let list = &paths["/users"]["get"];
+        assert_eq!(list["operationId"], "list_users");
+        assert_eq!(
+            list["responses"]["200"]["content"]["application/json"]["schema"]["items"]["$ref"],
+            "#/components/schemas/User"
+        );
+        let names: Vec<&Value> = list["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| &p["name"])
+            .collect();
+        assert_eq!(
+            names,
+            ["id", "name", "email", "tags", "active", "verified", "sort", "limit", "offset"]
+        );
+
+        let patch = &paths["/users/{id}"]["patch"];
+        assert_eq!(
+            patch["parameters"][0]["schema"],
+            json!({ "type": "integer" })
+        );
+        let body = &patch["requestBody"]["content"]["application/json"]["schema"];
+        assert_eq!(body.get("required"), None);
+        for status in ["400", "404", "422"] {
+            assert!(patch["responses"][status].is_object());
+        }
+        for (path, method) in [("/users", "post"), ("/users/{id}", "put")] {
+            let responses = &paths[path][method]["responses"];
+            assert!(responses["400"].is_object() && responses["422"].is_object());
+        }
+
+        let yaml = api.to_yaml().unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(&parsed, api.document());
+        let parsed: Value = serde_json::from_str(&api.to_json().unwrap()).unwrap();
+        assert_eq!(&parsed, api.document());
+    }
+
+    #[tokio::test]
+    async fn test_into_router() {
+        let api = OpenApi::new("Users", "1.0.0").resource::<User>("/users", &CrudRoute::ALL);
+        let app: Router = api.clone().into_router();
+        let request = Request::get("/openapi.json").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(&body, api.document());
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{ident::quote_column, Column, Error};
+
+/// The direction of an `order by` term
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl Order {
+    fn as_sql(&self) -> &'static str {
+        match self {
+            Order::Asc => "asc",
+            Order::Desc => "desc",
+        }
+    }
+}
+
+/// Ordering and paging applied to a `select` query
+///
+/// # Examples
+/// ```
+/// use sqlx_model::{Order, SelectOptions};
+///
+/// let options = SelectOptions::new()
+///     .order_by("created_at", Order::Desc)
+///     .order_by("id", Order::Asc)
+///     .limit(20)
+///     .offset(40);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SelectOptions {
+    pub order_by: Vec<(String, Order)>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+impl SelectOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `col` to the `order by` clause. Earlier calls take precedence
+    pub fn order_by(mut self, col: &str, order: Order) -> Self {
+        self.order_by.push((col.to_string(), order));
+        self
+    }
+
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Render the options as the tail of a `select` statement, starting with a space if there
+    /// is anything to render
+    ///
+    /// # Arguments
+    /// - columns: The known columns of the table. Every `order_by` column must be one of them,
+    ///   unless the slice is empty.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidIdentifier` or `Error::UnknownColumn` if an `order_by` column is
+    /// not a valid identifier, or is not one of `columns`
+    pub fn to_sql(&self, columns: &[Column]) -> Result<String, Error> {
+        let mut sql = String::new();
+        if !self.order_by.is_empty() {
+            let terms = self
+                .order_by
+                .iter()
+                .map(|(col, order)| {
+                    Ok(format!(
+                        "{} {}",
+                        quote_column(col, columns)?,
+                        order.as_sql()
+                    ))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            sql.push_str(&format!(" order by {}", terms.join(",")));
+        }
+        match (self.limit, self.offset) {
+            (Some(limit), Some(offset)) => {
+                sql.push_str(&format!(" limit {} offset {}", limit, offset))
+            }
+            (Some(limit), None) => sql.push_str(&format!(" limit {}", limit)),
+            // SQLite only accepts an offset after a limit, and treats a negative limit as no limit
+            (None, Some(offset)) => sql.push_str(&format!(" limit -1 offset {}", offset)),
+            (None, None) => {}
+        }
+        Ok(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Order, SelectOptions};
+    use crate::Column;
+
+    #[test]
+    fn test_to_sql() {
+        assert_eq!(SelectOptions::new().to_sql(&[]).unwrap(), "");
+
+        let options = SelectOptions::new()
+            .order_by("created_at", Order::Desc)
+            .order_by("id", Order::Asc)
+            .limit(20)
+            .offset(40);
+        assert_eq!(
+            options.to_sql(&[]).unwrap(),
+            " order by \"created_at\" desc,\"id\" asc limit 20 offset 40"
+        );
+
+        let options = SelectOptions::new().offset(5);
+        assert_eq!(options.to_sql(&[]).unwrap(), " limit -1 offset 5");
+    }
+
+    #[test]
+    fn test_to_sql_columns() {
+        let columns = [Column::new("id")];
+        let options = SelectOptions::new().order_by("id", Order::Desc);
+        assert!(options.to_sql(&columns).is_ok());
+
+        let options = SelectOptions::new().order_by("name", Order::Desc);
+        assert!(options.to_sql(&columns).is_err());
+
+        let options = SelectOptions::new().order_by("id; drop table users", Order::Asc);
+        assert!(options.to_sql(&[]).is_err());
+    }
+}
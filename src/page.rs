@@ -0,0 +1,65 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Serialize;
+
+use crate::Error;
+
+/// One page of results from `SqliteModel::paginate`
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The cursor to pass to `paginate` to fetch the page after this one. `None` on the last
+    /// page
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+}
+
+/// Encode the sort key values of the last row of a page as an opaque, URL safe cursor
+pub(crate) fn encode_cursor(vals: &[serde_json::Value]) -> Result<String, Error> {
+    Ok(URL_SAFE_NO_PAD.encode(serde_json::to_vec(vals)?))
+}
+
+/// Decode a cursor made by [`encode_cursor`], checking that it holds one value per sort key
+///
+/// # Errors
+/// Returns `Error::InvalidCursor` if `cursor` is not valid base64 encoded JSON, or does not
+/// hold `key_count` values
+pub(crate) fn decode_cursor(
+    cursor: &str,
+    key_count: usize,
+) -> Result<Vec<serde_json::Value>, Error> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(cursor)
+        .map_err(|e| Error::InvalidCursor(format!("{:?}: {}", cursor, e)))?;
+    let vals: Vec<serde_json::Value> = serde_json::from_slice(&bytes)
+        .map_err(|e| Error::InvalidCursor(format!("{:?}: {}", cursor, e)))?;
+    if vals.len() != key_count {
+        return Err(Error::InvalidCursor(format!(
+            "{:?}: expected {} sort values, found {}",
+            cursor,
+            key_count,
+            vals.len()
+        )));
+    }
+    Ok(vals)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{decode_cursor, encode_cursor};
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let vals = vec![json!("alice"), json!(4)];
+        let cursor = encode_cursor(&vals).unwrap();
+        assert!(cursor
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(decode_cursor(&cursor, 2).unwrap(), vals);
+
+        assert!(decode_cursor(&cursor, 1).is_err());
+        assert!(decode_cursor("not a cursor!", 2).is_err());
+        assert!(decode_cursor(&encode_cursor(&[]).unwrap()[1..], 0).is_err());
+    }
+}
@@ -0,0 +1,497 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use axum::{
+    extract::{rejection::JsonRejection, FromRef, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::MethodRouter,
+    Json, Router,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use sqlx::{sqlite::SqliteRow, FromRow, SqlitePool};
+
+use crate::{extract::unknown_field, Affinity, Error, Loaded, ModelQuery, SqliteModel};
+
+/// One of the routes mounted by [`crud_router`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrudRoute {
+    /// `GET /`, the records of the table matching the query string, see [`ModelQuery`]
+    List,
+    /// `GET /{id}`, the record with the given key
+    Get,
+    /// `POST /`, insert the record in the body
+    Create,
+    /// `PUT /{id}`, insert or replace the record with the given key
+    Replace,
+    /// `PATCH /{id}`, update only the fields present in the body
+    Update,
+    /// `DELETE /{id}`, delete the record with the given key
+    Delete,
+}
+
+impl CrudRoute {
+    pub const ALL: [CrudRoute; 6] = [
+        CrudRoute::List,
+        CrudRoute::Get,
+        CrudRoute::Create,
+        CrudRoute::Replace,
+        CrudRoute::Update,
+        CrudRoute::Delete,
+    ];
+}
+
+/// Builds an Axum `Router` with create, read, update and delete routes for `M`, created by
+/// [`crud_router`]
+pub struct CrudRouter<M> {
+    disabled: Vec<CrudRoute>,
+    _model: PhantomData<fn() -> M>,
+}
+
+/// Create, read, update and delete routes for `M`, meant to be nested under the path of the
+/// resource, eg `Router::new().nest("/users", crud_router::<User>().into_router())`.
+///
+/// Records are identified by the primary key declared in `M::columns`, or by an `id` column if
+/// the model does not declare its columns. The `sqlx::SqlitePool` is taken from the state of
+/// the router. Errors are returned as `M::Error`, which must implement `IntoResponse` as
+/// `sqlx_model::Error` does.
+///
+/// | Route          | Method                       | Success                |
+/// |----------------|------------------------------|------------------------|
+/// | `GET /`        | `select_where_with`          | `200` with the models  |
+/// | `GET /{id}`    | `select_one`                 | `200` with the model   |
+/// | `POST /`       | `insert`                     | `201` with the model   |
+/// | `PUT /{id}`    | `upsert` on the key          | `200` with the model   |
+/// | `PATCH /{id}`  | `update` of the given fields | `200` with the model   |
+/// | `DELETE /{id}` | `delete`                     | `204`                  |
+///
+/// # Examples
+/// ```ignore
+/// let app: Router = Router::new()
+///     .nest("/users", crud_router::<User>().without(CrudRoute::Delete).into_router())
+///     .with_state(pool);
+/// ```
+pub fn crud_router<M: SqliteModel>() -> CrudRouter<M> {
+    CrudRouter {
+        disabled: Vec::new(),
+        _model: PhantomData,
+    }
+}
+
+impl<M> CrudRouter<M>
+where
+    M: SqliteModel
+        + for<'r> FromRow<'r, SqliteRow>
+        + Serialize
+        + DeserializeOwned
+        + Unpin
+        + Send
+        + Sync
+        + Debug
+        + 'static,
+    M::Error: IntoResponse + Send,
+{
+    /// Leave `route` out of the router
+    pub fn without(mut self, route: CrudRoute) -> Self {
+        self.disabled.push(route);
+        self
+    }
+
+    /// The routes the router will mount, eg to describe them with `OpenApi::resource`
+    pub fn routes(&self) -> Vec<CrudRoute> {
+        let enabled = CrudRoute::ALL.into_iter();
+        enabled.filter(|r| !self.disabled.contains(r)).collect()
+    }
+
+    pub fn into_router<S>(self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+        SqlitePool: FromRef<S>,
+    {
+        let enabled = |route| !self.disabled.contains(&route);
+        let mut root = MethodRouter::new();
+        if enabled(CrudRoute::List) {
+            root = root.get(list::<M>);
+        }
+        if enabled(CrudRoute::Create) {
+            root = root.post(create::<M>);
+        }
+        let mut item = MethodRouter::new();
+        if enabled(CrudRoute::Get) {
+            item = item.get(get::<M>);
+        }
+        if enabled(CrudRoute::Replace) {
+            item = item.put(replace::<M>);
+        }
+        if enabled(CrudRoute::Update) {
+            item = item.patch(update::<M>);
+        }
+        if enabled(CrudRoute::Delete) {
+            item = item.delete(delete::<M>);
+        }
+        Router::new().route("/", root).route("/{id}", item)
+    }
+}
+
+/// The column records of `M` are identified by in the path
+pub(crate) fn key_column<M: SqliteModel>() -> &'static str {
+    M::primary_key().unwrap_or("id")
+}
+
+/// The struct field the key column of `M` maps to
+fn key_field<M: SqliteModel>() -> &'static str {
+    let key = M::columns().iter().find(|c| c.primary_key);
+    key.map(|c| c.field).unwrap_or("id")
+}
+
+/// Convert the key taken from the path into the value of the key column. Keys which look like
+/// integers are treated as integers unless the key column is declared as text
+pub(crate) fn key_value<M: SqliteModel>(key: String) -> Value {
+    let key_col = M::columns().iter().find(|c| c.primary_key);
+    let text = key_col.is_some_and(|c| c.affinity == Some(Affinity::Text));
+    match key.parse::<i64>() {
+        Ok(i) if !text => Value::from(i),
+        _ => Value::from(key),
+    }
+}
+
+/// The fields of a request body, which must be a JSON object
+///
+/// # Errors
+/// Returns `Error::InvalidParameter` if the body is not a JSON object
+fn body_fields(
+    body: Result<Json<Map<String, Value>>, JsonRejection>,
+) -> Result<Map<String, Value>, Error> {
+    body.map(|Json(fields)| fields)
+        .map_err(|e| Error::InvalidParameter(e.body_text()))
+}
+
+/// Deserialize `fields` into a model
+///
+/// # Errors
+/// Returns `Error::InvalidParameter` if the body does not describe a model
+fn from_fields<M>(fields: Map<String, Value>) -> Result<M, Error>
+where
+    M: SqliteModel + DeserializeOwned,
+{
+    serde_json::from_value(Value::Object(fields))
+        .map_err(|e| Error::InvalidParameter(format!("invalid {}: {}", M::table_name(), e)))
+}
+
+/// Deserialize `fields` into a model, with the key field set to `key` whatever the body held
+///
+/// # Errors
+/// Returns `Error::InvalidParameter` if the body does not describe a model
+fn with_key<M>(mut fields: Map<String, Value>, key: Value) -> Result<M, Error>
+where
+    M: SqliteModel + DeserializeOwned,
+{
+    fields.insert(key_field::<M>().to_string(), key);
+    from_fields(fields)
+}
+
+/// The column each field of `fields` is stored in, leaving out the key and the columns `update`
+/// manages itself, such as timestamps and the version
+///
+/// # Errors
+/// Returns `Error::InvalidParameter` if a field is not a field of `M`
+fn field_columns<M: SqliteModel>(fields: &Map<String, Value>) -> Result<Vec<String>, Error> {
+    let mut columns = Vec::new();
+    for field in fields.keys().filter(|f| *f != key_field::<M>()) {
+        let column = match M::columns() {
+            [] => field.as_str(),
+            declared => match declared.iter().find(|c| c.field == field) {
+                Some(c) if c.timestamp.is_some() || c.version => continue,
+                Some(c) => c.name,
+                None => return Err(unknown_field::<M>(field)),
+            },
+        };
+        columns.push(column.to_string());
+    }
+    Ok(columns)
+}
+
+async fn list<M>(
+    State(pool): State<SqlitePool>,
+    query: ModelQuery<M>,
+) -> Result<Json<Vec<M>>, M::Error>
+where
+    M: SqliteModel + for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
+{
+    Ok(Json(query.fetch(&pool).await?))
+}
+
+async fn get<M>(Loaded(model): Loaded<M>) -> Json<M> {
+    Json(model)
+}
+
+async fn create<M>(
+    State(pool): State<SqlitePool>,
+    body: Result<Json<Map<String, Value>>, JsonRejection>,
+) -> Result<(StatusCode, Json<M>), M::Error>
+where
+    M: SqliteModel
+        + for<'r> FromRow<'r, SqliteRow>
+        + Serialize
+        + DeserializeOwned
+        + Unpin
+        + Send
+        + Sync
+        + Debug,
+{
+    let model: M = from_fields(body_fields(body)?)?;
+    let created = model.insert(&pool, &[]).await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+async fn replace<M>(
+    State(pool): State<SqlitePool>,
+    Path(key): Path<String>,
+    body: Result<Json<Map<String, Value>>, JsonRejection>,
+) -> Result<Json<M>, M::Error>
+where
+    M: SqliteModel
+        + for<'r> FromRow<'r, SqliteRow>
+        + Serialize
+        + DeserializeOwned
+        + Unpin
+        + Send
+        + Sync
+        + Debug,
+{
+    let model: M = with_key(body_fields(body)?, key_value::<M>(key))?;
+    Ok(Json(model.upsert(&pool, &[], key_column::<M>()).await?))
+}
+
+async fn update<M>(
+    State(pool): State<SqlitePool>,
+    Path(key): Path<String>,
+    body: Result<Json<Map<String, Value>>, JsonRejection>,
+) -> Result<Json<M>, M::Error>
+where
+    M: SqliteModel
+        + for<'r> FromRow<'r, SqliteRow>
+        + Serialize
+        + DeserializeOwned
+        + Unpin
+        + Send
+        + Sync
+        + Debug,
+{
+    let patch = body_fields(body)?;
+    let key = key_value::<M>(key);
+    let existing = M::select_one(&pool, key_column::<M>(), key.clone()).await?;
+    let columns = field_columns::<M>(&patch)?;
+    if columns.is_empty() {
+        return Ok(Json(existing));
+    }
+    let mut fields = match serde_json::to_value(&existing).map_err(Error::from)? {
+        Value::Object(fields) => fields,
+        _ => Map::new(),
+    };
+    fields.extend(patch);
+    let model: M = with_key(fields, key)?;
+    let columns: Vec<&str> = columns.iter().map(String::as_str).collect();
+    let updated = model
+        .update(&pool, key_column::<M>(), Some(&columns))
+        .await?;
+    Ok(Json(updated))
+}
+
+async fn delete<M>(
+    State(pool): State<SqlitePool>,
+    Path(key): Path<String>,
+) -> Result<StatusCode, M::Error>
+where
+    M: SqliteModel + for<'r> FromRow<'r, SqliteRow> + Unpin + Send + Sync,
+{
+    let key = key_value::<M>(key);
+    let deleted = M::delete(&pool, key_column::<M>(), key).await?;
+    if deleted.is_empty() {
+        return Err(Error::NotFound {
+            table: M::table_name(),
+            column: key_column::<M>().to_string(),
+        })?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use super::{crud_router, CrudRoute};
+    use crate::{IfNotExists, SqliteModel};
+
+    #[derive(Debug, Serialize, Deserialize, crate::SqliteModel)]
+    #[model(table = "users")]
+    struct User {
+        #[model(primary_key, skip_insert)]
+        id: i64,
+        #[model(rename = "user_name", unique)]
+        name: String,
+        #[model(validate(email))]
+        email: Option<String>,
+    }
+
+    async fn app(router: Router<sqlx::SqlitePool>) -> Router {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        User::create_table(&pool, IfNotExists(false)).await.unwrap();
+        router.with_state(pool)
+    }
+
+    async fn send(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => request
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        };
+        let response = app.clone().oneshot(request.unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn test_crud_router() {
+        let app = app(crud_router::<User>().into_router()).await;
+
+        let alice = json!({"id": 0, "name": "alice", "email": null});
+        let (status, body) = send(&app, "POST", "/", Some(alice)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body, json!({"id": 1, "name": "alice", "email": null}));
+        let bob = json!({"id": 0, "name": "bob", "email": "bob@example.com"});
+        send(&app, "POST", "/", Some(bob)).await;
+
+        let (status, body) = send(&app, "GET", "/", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.as_array().unwrap().len(), 2);
+        let (status, body) = send(&app, "GET", "/?email[null]=false&sort=-id", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            json!([{"id": 2, "name": "bob", "email": "bob@example.com"}])
+        );
+        let (status, _) = send(&app, "GET", "/?user_name=bob", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = send(&app, "GET", "/2", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["name"], "bob");
+        let (status, _) = send(&app, "GET", "/3", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let carol = json!({"id": 1, "name": "carol", "email": null});
+        let (status, body) = send(&app, "PUT", "/7", Some(carol)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({"id": 7, "name": "carol", "email": null}));
+
+        let patch = json!({"email": "carol@example.com"});
+        let (status, body) = send(&app, "PATCH", "/7", Some(patch)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            json!({"id": 7, "name": "carol", "email": "carol@example.com"})
+        );
+        let (status, _) = send(&app, "PATCH", "/8", Some(json!({"name": "x"}))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, body) = send(&app, "PATCH", "/7", Some(json!({"age": 3}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body["detail"],
+            "invalid parameter: \"age\" is not a field of users"
+        );
+        let (status, _) = send(&app, "PATCH", "/7", Some(json!({"name": 3}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = send(&app, "PUT", "/7", Some(json!({"email": null}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        for method in ["POST", "PUT", "PATCH"] {
+            let uri = if method == "POST" { "/" } else { "/7" };
+            for body in [json!({"id": 0, "name": 3, "email": null}), json!(["carol"])] {
+                let (status, body) = send(&app, method, uri, Some(body)).await;
+                assert_eq!(status, StatusCode::BAD_REQUEST);
+                assert_eq!(body["status"], 400);
+            }
+        }
+
+        let bob = json!({"id": 0, "name": "bob", "email": null});
+        let (status, body) = send(&app, "POST", "/", Some(bob)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["status"], 409);
+
+        let erin = json!({"id": 0, "name": "erin", "email": "erin"});
+        let (status, body) = send(&app, "POST", "/", Some(erin)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["errors"][0]["field"], "email");
+
+        let (status, _) = send(&app, "DELETE", "/1", None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&app, "DELETE", "/1", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (_, body) = send(&app, "GET", "/", None).await;
+        assert_eq!(body.as_array().unwrap().len(), 2);
+    }
+
+    #[derive(Debug, Serialize, Deserialize, crate::SqliteModel)]
+    #[model(table = "notes")]
+    struct Note {
+        #[model(primary_key, skip_insert)]
+        id: i64,
+        body: String,
+        #[model(deleted_at)]
+        deleted_at: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_replace_trashed() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        Note::create_table(&pool, IfNotExists(false)).await.unwrap();
+        let app = crud_router::<Note>().into_router().with_state(pool);
+
+        let (status, _) = send(&app, "POST", "/", Some(json!({"id": 0, "body": "a"}))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = send(&app, "DELETE", "/1", None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&app, "GET", "/1", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, body) = send(&app, "PUT", "/1", Some(json!({"body": "b"}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({"id": 1, "body": "b", "deleted_at": null}));
+        let (status, body) = send(&app, "GET", "/1", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["body"], "b");
+    }
+
+    #[tokio::test]
+    async fn test_crud_router_without() {
+        let router = crud_router::<User>()
+            .without(CrudRoute::Delete)
+            .without(CrudRoute::Create)
+            .into_router();
+        let app = app(router).await;
+
+        let (status, _) = send(&app, "POST", "/", Some(json!({"name": "alice"}))).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        let (status, _) = send(&app, "DELETE", "/1", None).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        let (status, body) = send(&app, "GET", "/", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!([]));
+    }
+}
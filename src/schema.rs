@@ -0,0 +1,507 @@
+use sqlx::Acquire;
+
+use crate::{
+    ident::{quote_column, quote_ident},
+    Affinity, Column, Error, SqliteModel,
+};
+
+/// Whether `create table` should succeed without doing anything when the table already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IfNotExists(pub bool);
+
+/// Render the `create table` statement of `table` from the metadata of its columns
+///
+/// # Arguments
+/// - table: The unquoted name of the table.
+/// - columns: The columns of the table, in the order they are declared.
+/// - if_not_exists: Whether to add `if not exists` to the statement.
+///
+/// # Errors
+/// Returns `Error::InvalidQuery` if `columns` is empty, and `Error::InvalidIdentifier` if the
+/// table or a column is not a valid identifier
+pub(crate) fn create_table_sql(
+    table: &str,
+    columns: &[Column],
+    if_not_exists: IfNotExists,
+) -> Result<String, Error> {
+    if columns.is_empty() {
+        return Err(Error::InvalidQuery(format!(
+            "create table: the columns of {} are unknown",
+            table
+        )));
+    }
+    let defs = columns
+        .iter()
+        .map(|c| column_sql(c, columns))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let if_not_exists = match if_not_exists {
+        IfNotExists(true) => "if not exists ",
+        IfNotExists(false) => "",
+    };
+    Ok(format!(
+        "create table {}{} (\n    {}\n);",
+        if_not_exists,
+        quote_ident(table)?,
+        defs.join(",\n    "),
+    ))
+}
+
+/// Render the definition of `column` inside a `create table` statement
+pub(crate) fn column_sql(column: &Column, columns: &[Column]) -> Result<String, Error> {
+    let mut def = quote_column(column.name, columns)?;
+    if let Some(affinity) = column.affinity {
+        def.push(' ');
+        def.push_str(affinity.as_sql());
+    }
+    if column.primary_key {
+        def.push_str(" primary key");
+    }
+    if column.not_null {
+        def.push_str(" not null");
+    }
+    if column.unique {
+        def.push_str(" unique");
+    }
+    if let Some(default) = column.default {
+        def.push_str(&format!(" default {}", default_sql(default)));
+    }
+    Ok(def)
+}
+
+/// Render a `default` expression. Anything other than a literal must be wrapped in parentheses
+pub(crate) fn default_sql(expr: &str) -> String {
+    match is_literal(expr) {
+        true => expr.to_string(),
+        false => format!("({})", expr),
+    }
+}
+
+/// Whether `expr` is a literal value rather than an expression, which decides whether it can be
+/// the default of a column added with `alter table`
+pub(crate) fn is_literal(expr: &str) -> bool {
+    let expr = expr.trim();
+    // A string or blob literal, with no unescaped quote in between that could end it early
+    let quoted = |s: &str| {
+        s.len() >= 2
+            && s.starts_with('\'')
+            && s.ends_with('\'')
+            && !s[1..s.len() - 1].replace("''", "").contains('\'')
+    };
+    let number = expr.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c))
+        && expr.parse::<f64>().is_ok();
+    number
+        || quoted(expr)
+        || ((expr.starts_with('x') || expr.starts_with('X')) && quoted(&expr[1..]))
+        || ["null", "true", "false"]
+            .iter()
+            .any(|k| expr.eq_ignore_ascii_case(k))
+}
+
+/// One way a column of the live table differs from the column the model declares
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    Affinity {
+        expected: Affinity,
+        found: Option<Affinity>,
+    },
+    NotNull {
+        expected: bool,
+        found: bool,
+    },
+    PrimaryKey {
+        expected: bool,
+        found: bool,
+    },
+    Unique {
+        expected: bool,
+        found: bool,
+    },
+}
+
+/// The differences between the columns a model declares and the columns of its live table,
+/// as found by [`verify_schema`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    /// Columns declared by the model which the table does not have. If the table does not exist,
+    /// every column is missing
+    pub missing_columns: Vec<String>,
+    /// Columns of the table which the model does not declare
+    pub extra_columns: Vec<String>,
+    /// Columns present on both sides whose definitions differ, paired with how they differ
+    pub mismatches: Vec<(String, Mismatch)>,
+}
+
+impl SchemaDiff {
+    /// Whether the table matches the model
+    pub fn is_empty(&self) -> bool {
+        self.missing_columns.is_empty()
+            && self.extra_columns.is_empty()
+            && self.mismatches.is_empty()
+    }
+}
+
+/// A column of a live table, as reported by SQLite's `table_info` and `index_list` pragmas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TableColumn {
+    pub name: String,
+    pub declared_type: String,
+    pub not_null: bool,
+    pub primary_key: bool,
+    /// Whether a single column unique index covers the column, not counting the primary key
+    pub unique: bool,
+    pub default: Option<String>,
+}
+
+/// Read the columns of `table`, in the order they are declared. A table which does not exist has
+/// no columns
+pub(crate) async fn table_columns(
+    conn: &mut sqlx::SqliteConnection,
+    table: &str,
+) -> Result<Vec<TableColumn>, Error> {
+    let rows: Vec<(String, String, bool, Option<String>, i64)> = sqlx::query_as(
+        r#"select name, type, "notnull", dflt_value, pk from pragma_table_info(?) order by cid"#,
+    )
+    .bind(table)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // Unique indexes made by `unique` constraints or `create unique index`, leaving out the
+    // primary key, which `table_info` already reports
+    let unique_columns: Vec<String> = sqlx::query_scalar(
+        r#"select min(info.name) from pragma_index_list(?) as list,
+            pragma_index_info(list.name) as info
+        where list."unique" = 1 and list.origin != 'pk'
+        group by list.name
+        having count(*) = 1"#,
+    )
+    .bind(table)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(name, declared_type, not_null, default, pk)| TableColumn {
+            unique: unique_columns.contains(&name),
+            name,
+            declared_type,
+            not_null,
+            primary_key: pk > 0,
+            default,
+        })
+        .collect())
+}
+
+/// The `create index` statements of the indexes explicitly created on `table`, each paired with
+/// the columns it covers
+pub(crate) async fn table_indexes(
+    conn: &mut sqlx::SqliteConnection,
+    table: &str,
+) -> Result<Vec<(String, Vec<String>)>, Error> {
+    let indexes: Vec<(String, String)> = sqlx::query_as(
+        "select name, sql from sqlite_master where type = 'index' and tbl_name = ? and sql is not null",
+    )
+    .bind(table)
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut with_columns = Vec::with_capacity(indexes.len());
+    for (name, sql) in indexes {
+        // Expressions have no name, so an index on an expression only lists its plain columns
+        let columns: Vec<Option<String>> =
+            sqlx::query_scalar("select name from pragma_index_info(?) order by seqno")
+                .bind(name)
+                .fetch_all(&mut *conn)
+                .await?;
+        with_columns.push((sql, columns.into_iter().flatten().collect()));
+    }
+    Ok(with_columns)
+}
+
+/// The affinity SQLite gives a column declared with `declared_type`, following the rules of
+/// <https://www.sqlite.org/datatype3.html#determination_of_column_affinity>. `None` for a column
+/// declared without a type
+pub(crate) fn declared_affinity(declared_type: &str) -> Option<Affinity> {
+    let declared = declared_type.to_uppercase();
+    let affinity = if declared.is_empty() {
+        return None;
+    } else if declared.contains("INT") {
+        Affinity::Integer
+    } else if ["CHAR", "CLOB", "TEXT"]
+        .iter()
+        .any(|t| declared.contains(t))
+    {
+        Affinity::Text
+    } else if declared.contains("BLOB") {
+        Affinity::Blob
+    } else if ["REAL", "FLOA", "DOUB"]
+        .iter()
+        .any(|t| declared.contains(t))
+    {
+        Affinity::Real
+    } else {
+        Affinity::Numeric
+    };
+    Some(affinity)
+}
+
+/// Compare the columns `M` declares with the columns of its live table, so drift can be caught
+/// at startup rather than by a failing query
+///
+/// Affinities are only compared for columns that declare one, and nullability is not compared
+/// for primary key columns, since SQLite never stores `NULL` in an integer primary key
+///
+/// # Arguments
+/// - conn: The sqlx::SqlitePool or connection to inspect.
+///
+/// # Returns
+/// - Result<SchemaDiff, Error>: The differences found, which are empty if the table matches.
+///
+/// # Errors
+/// Returns `Error::InvalidQuery` if `M` does not declare its `columns`, and `Error::Database`
+/// if the table cannot be inspected
+pub async fn verify_schema<'a, M>(
+    conn: impl Acquire<'a, Database = sqlx::Sqlite> + Send,
+) -> Result<SchemaDiff, Error>
+where
+    M: SqliteModel + ?Sized,
+{
+    let declared = M::columns();
+    if declared.is_empty() {
+        return Err(Error::InvalidQuery(format!(
+            "verify schema: the columns of {} are unknown",
+            M::table_name()
+        )));
+    }
+    let mut conn = conn.acquire().await?;
+    let found = table_columns(&mut conn, &M::table_name()).await?;
+    Ok(diff_columns(declared, &found))
+}
+
+/// Compare `declared` columns with the `found` columns of a live table, following the rules
+/// described on [`verify_schema`]
+pub(crate) fn diff_columns(declared: &[Column], found: &[TableColumn]) -> SchemaDiff {
+    let mut diff = SchemaDiff {
+        extra_columns: found
+            .iter()
+            .filter(|f| !declared.iter().any(|c| c.name == f.name))
+            .map(|f| f.name.clone())
+            .collect(),
+        ..SchemaDiff::default()
+    };
+    for column in declared {
+        let Some(found) = found.iter().find(|f| f.name == column.name) else {
+            diff.missing_columns.push(column.name.to_string());
+            continue;
+        };
+        let mut mismatches = Vec::new();
+        let found_affinity = declared_affinity(&found.declared_type);
+        match column.affinity {
+            Some(expected) if Some(expected) != found_affinity => {
+                mismatches.push(Mismatch::Affinity {
+                    expected,
+                    found: found_affinity,
+                })
+            }
+            _ => {}
+        }
+        if !column.primary_key && column.not_null != found.not_null {
+            mismatches.push(Mismatch::NotNull {
+                expected: column.not_null,
+                found: found.not_null,
+            });
+        }
+        if column.primary_key != found.primary_key {
+            mismatches.push(Mismatch::PrimaryKey {
+                expected: column.primary_key,
+                found: found.primary_key,
+            });
+        }
+        if column.unique != found.unique {
+            mismatches.push(Mismatch::Unique {
+                expected: column.unique,
+                found: found.unique,
+            });
+        }
+        diff.mismatches
+            .extend(mismatches.into_iter().map(|m| (column.name.to_string(), m)));
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::{
+        create_table_sql, declared_affinity, is_literal, verify_schema, IfNotExists, Mismatch,
+    };
+    use crate::{Affinity, Column, Error, SqliteModel};
+
+    #[test]
+    fn test_create_table_sql() {
+        let columns = [
+            Column::new("id")
+                .affinity(Affinity::Integer)
+                .primary_key()
+                .not_null(),
+            Column::new("email")
+                .affinity(Affinity::Text)
+                .not_null()
+                .unique(),
+            Column::new("created_at")
+                .affinity(Affinity::Integer)
+                .not_null()
+                .default_expr("unixepoch()"),
+            Column::new("extra"),
+        ];
+        assert_eq!(
+            create_table_sql("users", &columns, IfNotExists(true)).unwrap(),
+            "create table if not exists \"users\" (
+    \"id\" integer primary key not null,
+    \"email\" text not null unique,
+    \"created_at\" integer not null default (unixepoch()),
+    \"extra\"
+);"
+        );
+        assert!(create_table_sql("users", &columns, IfNotExists(false))
+            .unwrap()
+            .starts_with("create table \"users\" ("));
+
+        assert!(matches!(
+            create_table_sql("users", &[], IfNotExists(false)),
+            Err(Error::InvalidQuery(_))
+        ));
+        assert!(matches!(
+            create_table_sql("users; --", &columns, IfNotExists(false)),
+            Err(Error::InvalidIdentifier(_))
+        ));
+    }
+
+    #[test]
+    fn test_is_literal() {
+        for literal in ["0", "-1.5", "'it''s'", "x'00ff'", "NULL", "true"] {
+            assert!(is_literal(literal), "{}", literal);
+        }
+        for expr in [
+            "unixepoch()",
+            "'a' || 'b'",
+            "inf",
+            "1 + 1",
+            "current_timestamp",
+        ] {
+            assert!(!is_literal(expr), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_declared_affinity() {
+        assert_eq!(declared_affinity(""), None);
+        assert_eq!(declared_affinity("BIGINT"), Some(Affinity::Integer));
+        assert_eq!(declared_affinity("varchar(20)"), Some(Affinity::Text));
+        assert_eq!(declared_affinity("blob"), Some(Affinity::Blob));
+        assert_eq!(declared_affinity("DOUBLE PRECISION"), Some(Affinity::Real));
+        assert_eq!(declared_affinity("datetime"), Some(Affinity::Numeric));
+    }
+
+    #[derive(Debug, Serialize, crate::SqliteModel)]
+    #[model(table = "accounts")]
+    struct Account {
+        #[model(primary_key)]
+        id: i64,
+        #[model(unique)]
+        email: String,
+        nickname: Option<String>,
+        balance: f64,
+        settings: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_verify_schema() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        let diff = verify_schema::<Account>(&pool).await.unwrap();
+        assert_eq!(
+            diff.missing_columns,
+            vec!["id", "email", "nickname", "balance", "settings"]
+        );
+
+        Account::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        assert!(verify_schema::<Account>(&pool).await.unwrap().is_empty());
+
+        sqlx::query(
+            r"drop table accounts;
+            create table accounts (
+                id integer primary key,
+                email text not null,
+                nickname text not null,
+                balance text,
+                created_at integer
+            );",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let diff = verify_schema::<Account>(&pool).await.unwrap();
+        assert!(!diff.is_empty());
+        assert_eq!(diff.missing_columns, vec!["settings"]);
+        assert_eq!(diff.extra_columns, vec!["created_at"]);
+        assert_eq!(
+            diff.mismatches,
+            vec![
+                (
+                    "email".to_string(),
+                    Mismatch::Unique {
+                        expected: true,
+                        found: false
+                    }
+                ),
+                (
+                    "nickname".to_string(),
+                    Mismatch::NotNull {
+                        expected: false,
+                        found: true
+                    }
+                ),
+                (
+                    "balance".to_string(),
+                    Mismatch::Affinity {
+                        expected: Affinity::Real,
+                        found: Some(Affinity::Text)
+                    }
+                ),
+                (
+                    "balance".to_string(),
+                    Mismatch::NotNull {
+                        expected: true,
+                        found: false
+                    }
+                ),
+            ]
+        );
+
+        sqlx::query("create unique index accounts_email on accounts (email)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let diff = verify_schema::<Account>(&pool).await.unwrap();
+        assert!(!diff.mismatches.iter().any(|(c, _)| c == "email"));
+    }
+
+    #[tokio::test]
+    async fn test_verify_schema_unknown_columns() {
+        #[derive(Debug, Serialize)]
+        struct Plain {
+            id: i64,
+        }
+
+        impl SqliteModel for Plain {
+            type Error = Error;
+        }
+
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        assert!(matches!(
+            verify_schema::<Plain>(&pool).await,
+            Err(Error::InvalidQuery(_))
+        ));
+    }
+}
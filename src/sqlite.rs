@@ -1,21 +1,30 @@
 use std::fmt::Debug;
 
 use async_trait::async_trait;
-use serde::{ser::Error, Serialize};
+use serde::{ser::Error as _, Serialize};
 use sqlx::{sqlite::SqliteRow, FromRow};
 
-use crate::BasicType;
+use crate::{
+    aggregate::BasicRow,
+    hooks::{Hooks, Write},
+    ident::{quote_column, quote_ident},
+    page::{decode_cursor, encode_cursor},
+    schema::create_table_sql,
+    validate::validate_values,
+    Aggregate, BasicType, Column, Error, FieldRule, Filter, IfNotExists, Order, Page,
+    SelectOptions, TimeFormat, Timestamp,
+};
 
-fn bind_values<'q, T>(
-    query_str: &'q str,
-    vals: &'q [serde_json::Value],
-) -> Option<sqlx::query::QueryAs<'q, sqlx::Sqlite, T, sqlx::sqlite::SqliteArguments<'q>>>
+fn bind_values<T>(
+    query_str: &str,
+    vals: Vec<BasicType>,
+) -> sqlx::query::QueryAs<'_, sqlx::Sqlite, T, sqlx::sqlite::SqliteArguments<'_>>
 where
-    T: Send + 'q + for<'r> FromRow<'r, sqlx::sqlite::SqliteRow>,
+    T: Send + for<'r> FromRow<'r, sqlx::sqlite::SqliteRow>,
 {
     let mut query = sqlx::query_as(query_str);
     for val in vals {
-        query = match val_to_basic_type(val)? {
+        query = match val {
             BasicType::Null => query.bind(Option::<String>::None),
             BasicType::Real(f) => query.bind(f),
             BasicType::Text(s) => query.bind(s),
@@ -23,51 +32,362 @@ where
             BasicType::Integer(i) => query.bind(i),
         };
     }
-    Some(query)
+    query
 }
 
-fn val_to_basic_type(val: &serde_json::Value) -> Option<BasicType> {
-    match val {
-        serde_json::Value::Null => Some(BasicType::Null),
-        serde_json::Value::Bool(b) => Some(BasicType::Integer(if *b { 1 } else { 0 })),
-        serde_json::Value::Number(_) => val_to_basic_num(val),
-        serde_json::Value::String(s) => Some(BasicType::Text(s.to_string())),
-        serde_json::Value::Array(a) => Some(BasicType::Blob(val_to_blob(a)?)),
+/// The quoted name of the table of `M`
+fn table_ident<M: SqliteModel + ?Sized>() -> Result<String, Error> {
+    quote_ident(&M::table_name())
+}
+
+/// The quoted name of the column `name` of `M`, which must be one of `M::columns` if they are known
+fn column_ident<M: SqliteModel + ?Sized>(name: &str) -> Result<String, Error> {
+    quote_column(name, M::columns())
+}
+
+/// The placeholder to bind the value of the column `name` of `M` with. Values of `json` columns
+/// are passed through SQLite's `json()`, so malformed JSON is rejected before it is stored. The
+/// `updated_at` column is set to the current time instead, and binds no value
+fn placeholder<M: SqliteModel + ?Sized>(name: &str) -> &'static str {
+    match M::columns().iter().find(|c| c.name == name) {
+        Some(Column {
+            timestamp: Some(Timestamp::UpdatedAt(format)),
+            ..
+        }) => format.now_sql(),
+        Some(c) if c.json => "json(?)",
+        _ => "?",
+    }
+}
+
+/// Whether the column `name` of `M` is set to the current time by `placeholder` rather than
+/// bound to a value
+fn is_updated_at<M: SqliteModel + ?Sized>(name: &str) -> bool {
+    M::columns()
+        .iter()
+        .any(|c| c.name == name && matches!(c.timestamp, Some(Timestamp::UpdatedAt(_))))
+}
+
+/// Whether the column `name` of `M` is the time its record was created or soft deleted, which
+/// `upsert` and `update` never write
+fn is_unwritten<M: SqliteModel + ?Sized>(name: &str) -> bool {
+    M::columns().iter().any(|c| {
+        c.name == name
+            && matches!(
+                c.timestamp,
+                Some(Timestamp::CreatedAt(_) | Timestamp::DeletedAt(_))
+            )
+    })
+}
+
+/// The column recording when a record of `M` was soft deleted, and the format of its values
+fn deleted_at<M: SqliteModel + ?Sized>() -> Option<(&'static str, TimeFormat)> {
+    M::columns().iter().find_map(|c| match c.timestamp {
+        Some(Timestamp::DeletedAt(format)) => Some((c.name, format)),
         _ => None,
+    })
+}
+
+/// The column counting the writes to a record of `M`, for optimistic locking
+fn version_column<M: SqliteModel + ?Sized>() -> Option<&'static str> {
+    M::columns().iter().find(|c| c.version).map(|c| c.name)
+}
+
+/// The error for a write which found no record of `M` with the version it expected
+fn conflict<M: SqliteModel + ?Sized>() -> Error {
+    Error::Conflict {
+        table: M::table_name(),
+        column: version_column::<M>().unwrap_or_default().to_string(),
+    }
+}
+
+/// A copy of `model` changed by its `before_*` hook for `write`, if `M` has hooks
+async fn before_write<M: SqliteModel + Sync>(
+    model: &M,
+    write: Write,
+) -> Result<Option<M>, M::Error> {
+    match M::hooks() {
+        Some(hooks) => Ok(Some(hooks.before(model, write).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Copies of `models` changed by their `before_*` hook for `write`, if `M` has hooks
+async fn before_write_all<M: SqliteModel + Sync>(
+    models: &[M],
+    write: Write,
+) -> Result<Option<Vec<M>>, M::Error> {
+    let Some(hooks) = M::hooks() else {
+        return Ok(None);
+    };
+    let mut hooked = Vec::with_capacity(models.len());
+    for model in models {
+        hooked.push(hooks.before(model, write).await?);
+    }
+    Ok(Some(hooked))
+}
+
+/// Runs the `after_*` hook for `write` on each of `models`, if `M` has hooks
+async fn after_write<M: SqliteModel + Sync>(models: &[M], write: Write) -> Result<(), M::Error> {
+    match M::hooks() {
+        Some(hooks) => hooks.after(models, write).await,
+        None => Ok(()),
+    }
+}
+
+/// Runs the `before_*` hook for `write` with `filter`, if `M` has hooks
+async fn before_where<M: SqliteModel>(filter: &Filter, write: Write) -> Result<(), M::Error> {
+    match M::hooks() {
+        Some(hooks) => hooks.before_where(filter, write).await,
+        None => Ok(()),
+    }
+}
+
+/// Which records of a soft deleted model a query sees
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trashed {
+    Without,
+    With,
+    Only,
+}
+
+/// Restrict `filter` to the records `trashed` selects, if `M` is soft deleted
+fn scoped<M: SqliteModel + ?Sized>(filter: &Filter, trashed: Trashed) -> Filter {
+    match (deleted_at::<M>(), trashed) {
+        (Some((col, _)), Trashed::Without) => filter.clone().and(Filter::is_null(col)),
+        (Some((col, _)), Trashed::Only) => filter.clone().and(Filter::is_not_null(col)),
+        _ => filter.clone(),
     }
 }
 
-fn val_to_blob(arr: &Vec<serde_json::Value>) -> Option<Vec<u8>> {
-    let mut blob = Vec::new();
-    for el in arr {
-        let basic = val_to_basic_num(el)?;
-        match basic {
-            BasicType::Integer(i) => blob.push(u8::try_from(i).ok()?),
-            _ => return None,
+/// The column recording when a record of `M` was soft deleted, for the operations which only
+/// make sense on soft deleted models
+fn require_deleted_at<M: SqliteModel + ?Sized>(
+    operation: &str,
+) -> Result<(&'static str, TimeFormat), Error> {
+    deleted_at::<M>().ok_or(Error::InvalidQuery(format!(
+        "{}: {} has no deleted_at column",
+        operation,
+        M::table_name()
+    )))
+}
+
+/// Select the records of `M` which match `filter`, among those `trashed` selects
+async fn select_scoped<'e, M, E>(
+    executor: E,
+    filter: &Filter,
+    options: &SelectOptions,
+    trashed: Trashed,
+) -> Result<Vec<M>, Error>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    M: SqliteModel + for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
+{
+    let (where_clause, vals) = scoped::<M>(filter, trashed).to_sql(M::columns())?;
+    let query_str = format!(
+        "select * from {} where {}{};",
+        table_ident::<M>()?,
+        where_clause,
+        options.to_sql(M::columns())?,
+    );
+    let query = bind_values(&query_str, vals);
+    Ok(query.fetch_all(executor).await?)
+}
+
+/// Remove every record of `M` which matches `filter`, soft deleted or not
+async fn hard_delete<'e, M, E>(executor: E, filter: &Filter) -> Result<Vec<M>, Error>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    M: SqliteModel + for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
+{
+    let (where_clause, vals) = filter.to_sql(M::columns())?;
+    let query_str = format!(
+        "delete from {} where {} returning *;",
+        table_ident::<M>()?,
+        where_clause
+    );
+    let query = bind_values(&query_str, vals);
+    Ok(query.fetch_all(executor).await?)
+}
+
+/// The largest number of parameters SQLite will bind to a single statement
+const MAX_BIND_PARAMS: usize = 32766;
+
+/// Upsert each of `models` with its own statement, all inside one transaction, for a model with
+/// a `version` column: the version each existing record must have is bound per statement, which
+/// a multi-row insert cannot do without also inserting it into new records
+async fn upsert_each<'a, M, A>(
+    conn: A,
+    models: &[M],
+    skip_cols: &[&str],
+    conflict_col: &str,
+) -> Result<Vec<M>, Error>
+where
+    A: sqlx::Acquire<'a, Database = sqlx::Sqlite> + Send,
+    M: SqliteModel + for<'r> FromRow<'r, SqliteRow> + Serialize + Unpin + Send + Sync + Debug,
+{
+    let mut tx = conn.begin().await?;
+    let mut saved = Vec::with_capacity(models.len());
+    for model in models {
+        let (query_str, vals) = upsert_sql(model, skip_cols, conflict_col)?;
+        let query = bind_values(&query_str, vals);
+        // Only a failed version check keeps a conflicting record from being updated, which
+        // rolls back the whole transaction
+        let row: Option<M> = query.fetch_optional(&mut *tx).await?;
+        saved.push(row.ok_or_else(conflict::<M>)?);
+    }
+    tx.commit().await?;
+    Ok(saved)
+}
+
+/// The `insert ... on conflict do update` statement of `upsert` for `model`, and its values.
+/// A new record is inserted with the default `version`, and an existing record is only updated
+/// if its `version` matches the one of `model`
+fn upsert_sql<M>(
+    model: &M,
+    skip_cols: &[&str],
+    conflict_col: &str,
+) -> Result<(String, Vec<BasicType>), Error>
+where
+    M: SqliteModel + Serialize + Debug,
+{
+    let table = table_ident::<M>()?;
+    let mut column_names = Vec::new();
+    let mut ordered_vals = Vec::new();
+    let mut update_vals = Vec::new();
+    let mut qmarks = Vec::new();
+    let mut update_clause = Vec::new();
+    let mut version_check = String::new();
+    let mut version_val = None;
+    for (col, val) in model.column_values()? {
+        if skip_cols.contains(&col.as_str()) || is_unwritten::<M>(&col) {
+            continue;
+        }
+        let qmark = placeholder::<M>(&col);
+        let quoted = column_ident::<M>(&col)?;
+        if version_column::<M>() == Some(col.as_str()) {
+            // Left out of the insert, so a new record starts from the column default
+            update_clause.push(format!("{} = {}.{} + 1", quoted, table, quoted));
+            version_check = format!(" where {}.{} = ?", table, quoted);
+            version_val = Some(BasicType::from_json(M::columns(), &col, &val)?);
+            continue;
+        }
+        if !is_updated_at::<M>(&col) {
+            let val = BasicType::from_json(M::columns(), &col, &val)?;
+            ordered_vals.push(val.clone());
+            update_vals.push(val);
         }
+        update_clause.push(format!("{} = {}", quoted, qmark));
+        column_names.push(quoted);
+        qmarks.push(qmark);
     }
-    Some(blob)
+    if let Some((deleted_at, _)) = deleted_at::<M>() {
+        // Writing a soft deleted record restores it
+        update_clause.push(format!("{} = null", column_ident::<M>(deleted_at)?));
+    }
+    let query_str = format!(
+        "insert into {} ({}) values ({}) on conflict({}) do update set {}{} returning *;",
+        table,
+        column_names.join(","),
+        qmarks.join(","),
+        column_ident::<M>(conflict_col)?,
+        update_clause.join(","),
+        version_check,
+    );
+    let vals = ordered_vals
+        .into_iter()
+        .chain(update_vals)
+        .chain(version_val);
+    Ok((query_str, vals.collect()))
 }
 
-fn val_to_basic_num(val: &serde_json::Value) -> Option<BasicType> {
-    if let serde_json::Value::Number(num) = val {
-        if let Some(n) = num.as_i64() {
-            return Some(BasicType::Integer(n));
+/// Collect the column names and the row-major values of `models` for a multi-row insert,
+/// leaving out any column in `skip_cols` and the `created_at` and `deleted_at` columns. The `updated_at` column
+/// is named, but has no values since its placeholder binds none
+fn bulk_values<M>(
+    models: &[M],
+    skip_cols: &[&str],
+) -> Result<(Vec<String>, Vec<Vec<BasicType>>), Error>
+where
+    M: SqliteModel + Serialize + Debug,
+{
+    let mut column_names: Vec<String> = Vec::new();
+    let mut rows = Vec::with_capacity(models.len());
+    for model in models {
+        let mut names = Vec::new();
+        let mut row = Vec::new();
+        for (col, val) in model.column_values()? {
+            if skip_cols.contains(&col.as_str()) || is_unwritten::<M>(&col) {
+                continue;
+            }
+            if !is_updated_at::<M>(&col) {
+                row.push(BasicType::from_json(M::columns(), &col, &val)?);
+            }
+            names.push(col);
         }
-        if let Some(n) = num.as_f64() {
-            return Some(BasicType::Real(n));
+        if rows.is_empty() {
+            column_names = names;
+        } else if names != column_names {
+            return Err(Error::InvalidQuery(format!(
+                "bulk insert: columns of {:?} do not match the columns {:?} of the first row",
+                model, column_names
+            )));
         }
-        return None;
+        rows.push(row);
+    }
+    Ok((column_names, rows))
+}
+
+/// Insert `models` with as few multi-row `insert` statements as SQLite's bound parameter limit
+/// allows, all inside one transaction. `on_conflict` receives the quoted column names and
+/// returns the clause appended after the values of every statement
+async fn bulk_insert<'a, M, A>(
+    conn: A,
+    models: &[M],
+    skip_cols: &[&str],
+    on_conflict: impl Fn(&[String]) -> Result<String, Error> + Send,
+) -> Result<Vec<M>, Error>
+where
+    A: sqlx::Acquire<'a, Database = sqlx::Sqlite> + Send,
+    M: SqliteModel + for<'r> FromRow<'r, SqliteRow> + Serialize + Unpin + Send + Sync + Debug,
+{
+    if models.is_empty() {
+        return Ok(Vec::new());
+    }
+    let (column_names, rows) = bulk_values(models, skip_cols)?;
+    let row_qmarks = column_names.iter().map(|c| placeholder::<M>(c));
+    let row_qmarks = format!("({})", row_qmarks.collect::<Vec<_>>().join(","));
+    let column_names = column_names
+        .iter()
+        .map(|c| column_ident::<M>(c))
+        .collect::<Result<Vec<_>, _>>()?;
+    let rows_per_chunk = (MAX_BIND_PARAMS / column_names.len().max(1)).max(1);
+    let conflict_clause = on_conflict(&column_names)?;
+
+    let mut tx = conn.begin().await?;
+    let mut saved = Vec::with_capacity(models.len());
+    for chunk in rows.chunks(rows_per_chunk) {
+        let query_str = format!(
+            "insert into {} ({}) values {}{} returning *;",
+            table_ident::<M>()?,
+            column_names.join(","),
+            vec![row_qmarks.as_str(); chunk.len()].join(","),
+            conflict_clause,
+        );
+        let query = bind_values(&query_str, chunk.concat());
+        saved.extend(query.fetch_all(&mut *tx).await?);
     }
-    None
+    tx.commit().await?;
+    Ok(saved)
 }
 
 #[async_trait]
 pub trait SqliteModel {
-    /// Custom error type for the model, which must implement the standard Error trait and be convertible from sqlx::Error
-    type Error: From<sqlx::Error> + From<serde_json::Error>;
+    /// Error type for the model, which must be convertible from this crate's `Error`.
+    /// `sqlx_model::Error` itself can be used directly, or wrapped in an application error type
+    type Error: From<Error>;
 
-    /// The name of this type in the database
+    /// The name of this type in the database. Every generated statement quotes it, so it must be
+    /// a valid identifier according to `is_valid_ident`
     ///
     /// # Errors
     /// The default implementation parses `std::any::type_name`, and will
@@ -81,124 +401,447 @@ pub trait SqliteModel {
             .to_string()
     }
 
+    /// Metadata for each column of the table.
+    ///
+    /// The default implementation returns an empty slice, meaning the columns are only known
+    /// once a model instance has been serialized. `#[derive(SqliteModel)]` overrides this with
+    /// the columns declared on the struct.
+    fn columns() -> &'static [Column] {
+        &[]
+    }
+
+    /// The name of the primary key column, if one is declared in `columns`
+    fn primary_key() -> Option<&'static str> {
+        Self::columns()
+            .iter()
+            .find(|c| c.primary_key)
+            .map(|c| c.name)
+    }
+
+    /// The column names of the model paired with their values, in the order they should be
+    /// written to the database.
+    ///
+    /// # Errors
+    /// The default implementation serializes the model with serde_json, and will return an
+    /// error if the model does not serialize into a map.
+    fn column_values(&self) -> Result<Vec<(String, serde_json::Value)>, serde_json::Error>
+    where
+        Self: Serialize + Debug,
+    {
+        match serde_json::to_value(self)? {
+            serde_json::Value::Object(m) => Ok(m.into_iter().collect()),
+            _ => Err(serde_json::Error::custom(format!(
+                "Failed to serialize {:?} into a map of column values",
+                &self,
+            ))),
+        }
+    }
+
+    /// The validation rules checked by `validate` before the model is written.
+    ///
+    /// The default implementation returns an empty slice. `#[derive(SqliteModel)]` overrides
+    /// this with the rules declared with `#[model(validate(...))]`.
+    fn rules() -> &'static [FieldRule] {
+        &[]
+    }
+
+    /// The largest `limit` a `ModelQuery` of the model accepts, which is also applied when the
+    /// query gives none. `None` leaves queries unbounded.
+    ///
+    /// The default implementation returns `None`. `#[derive(SqliteModel)]` overrides this with
+    /// `#[model(max_limit = ...)]`.
+    fn max_limit() -> Option<u64> {
+        None
+    }
+
+    /// The lifecycle hooks of the model, which `insert`, `upsert`, `update`, the delete
+    /// methods and their bulk variants run. `None` runs no hooks.
+    ///
+    /// The default implementation returns `None`. Implement `ModelHooks` and return
+    /// `Some(Hooks::new())` to run them, which `#[derive(SqliteModel)]` does with
+    /// `#[model(hooks)]`.
+    fn hooks() -> Option<Hooks<Self>>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Checks the values of the model against `rules`. `insert`, `upsert`, `update` and their
+    /// bulk variants call it after the `before_*` hook, so nothing is written if it fails.
+    ///
+    /// # Errors
+    /// Returns `Error::Validation` listing every broken rule, or another error if the model
+    /// cannot be serialized or a rule names an unknown column
+    fn validate(&self) -> Result<(), Self::Error>
+    where
+        Self: Serialize + Debug,
+    {
+        if Self::rules().is_empty() {
+            return Ok(());
+        }
+        let col_vals = self.column_values().map_err(Error::from)?;
+        Ok(validate_values(Self::columns(), Self::rules(), &col_vals)?)
+    }
+
+    /// The `create table` statement of the model, generated from `columns`, so it can be reviewed
+    /// or written to a migration file
+    ///
+    /// # Arguments
+    /// - if_not_exists: Whether the statement should do nothing if the table already exists.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidQuery` if the model does not declare its `columns`, and
+    /// `Error::InvalidIdentifier` if the table or a column name is not a valid identifier
+    fn ddl(if_not_exists: IfNotExists) -> Result<String, Self::Error> {
+        Ok(create_table_sql(
+            &Self::table_name(),
+            Self::columns(),
+            if_not_exists,
+        )?)
+    }
+
+    /// Creates the table of the model from the statement returned by `ddl`.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - if_not_exists: Whether to succeed without doing anything if the table already exists.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if the statement cannot be generated, or the database operation fails.
+    async fn create_table<'e, E>(executor: E, if_not_exists: IfNotExists) -> Result<(), Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        let query_str = Self::ddl(if_not_exists)?;
+        sqlx::query(&query_str)
+            .execute(executor)
+            .await
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
     /// Inserts a new record into the table and returns the newly created model instance.
     ///
     /// # Arguments
-    /// - pool: A reference to a sqlx::SqlitePool used for database interaction.
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
     /// - skip_cols: A list of column names to skip during the insertion. This can be useful for
     /// skipping columns that you would like to be set to their default value by the database. Eg
-    /// automatically setting and incrementing the primary key.
+    /// automatically setting and incrementing the primary key. Columns marked `skip_insert` in
+    /// `columns` are always skipped.
+    ///
+    /// If the model has `hooks`, `before_insert` is called on a copy of the model before it is
+    /// written, and `after_insert` with the saved model.
     ///
     /// # Returns
     /// - Result<Self, Self::Error>: Returns the newly inserted model instance on success, otherwise returns an error.
     ///
     /// # Errors
-    /// - Returns Self::Error if the database operation fails.
-    async fn insert(&self, pool: &sqlx::SqlitePool, skip_cols: &[&str]) -> Result<Self, Self::Error>
+    /// - Returns Self::Error if a hook fails or the database operation fails.
+    async fn insert<'e, E>(&self, executor: E, skip_cols: &[&str]) -> Result<Self, Self::Error>
     where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
         Self: Sized + for<'r> FromRow<'r, SqliteRow> + Serialize + Unpin + Send + Debug,
     {
+        let hooked = before_write(self, Write::Insert).await?;
+        let model = hooked.as_ref().unwrap_or(self);
+        model.validate()?;
         let mut column_names = Vec::new();
         let mut ordered_vals = Vec::new();
         let mut qmarks = Vec::new();
-        let map = match serde_json::to_value(self)? {
-            serde_json::Value::Object(m) => m,
-            _ => {
-                return Err(serde_json::Error::custom(format!(
-                    "Failed to serialize {:?} into a map while running an insert query.",
-                    &self,
-                )))?
-            }
-        };
-        for (col, val) in map {
-            if !skip_cols.contains(&col.as_str()) {
-                column_names.push(col.to_string());
-                ordered_vals.push(val);
-                qmarks.push("?");
+        let skip_insert = Self::columns().iter().filter(|c| c.skip_insert);
+        let skip_insert: Vec<&str> = skip_insert.map(|c| c.name).collect();
+        for (col, val) in model.column_values().map_err(Error::from)? {
+            if !skip_cols.contains(&col.as_str()) && !skip_insert.contains(&col.as_str()) {
+                ordered_vals.push(BasicType::from_json(Self::columns(), &col, &val)?);
+                column_names.push(column_ident::<Self>(&col)?);
+                qmarks.push(placeholder::<Self>(&col));
             }
         }
         let query_str = format!(
             "insert into {} ({}) values ({}) returning *;",
-            Self::table_name(),
+            table_ident::<Self>()?,
             column_names.join(","),
             qmarks.join(","),
         );
-        let query =
-            bind_values(&query_str, &ordered_vals).ok_or(serde_json::Error::custom(format!(
-                "Insert query: cannot parse attributes of {:?} into Sqlite compatible types",
-                &self
-            )))?;
-        Ok(query.fetch_one(pool).await?)
+        let query = bind_values(&query_str, ordered_vals);
+        let saved: Self = query.fetch_one(executor).await.map_err(Error::from)?;
+        after_write(std::slice::from_ref(&saved), Write::Insert).await?;
+        Ok(saved)
     }
 
     /// Inserts or updates a record in the table depending on whether a conflict occurs on a specific column.
     ///
     /// # Arguments
-    /// - pool: A reference to a sqlx::SqlitePool used for database interaction.
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
     /// - skip_cols: A list of column names to skip during the insertion. This can be useful for
     /// skipping columns that you would like to be set to their default value by the database. Eg
     /// automatically setting and incrementing the primary key.
     /// - conflict_col: The name of the column to check for conflicts (usually the primary key).
     ///
+    /// If the model has `hooks`, `before_upsert` is called on a copy of the model before it is
+    /// written, and `after_upsert` with the saved model. The `created_at` column is never
+    /// written, and the `updated_at` column is set to the current time, as declared in
+    /// `columns`. If a `version` column is declared, an existing record is only updated if its
+    /// version matches the model, and its version is incremented, while a new record starts from
+    /// the default of the column. An existing record which is soft deleted is updated and
+    /// restored, without calling the `restore` hooks.
+    ///
     /// # Returns
     /// - Result<Self, Self::Error>: Returns the upserted model instance on success, otherwise returns an error.
     ///
     /// # Errors
-    /// - Returns Self::Error if the database operation fails.
-    async fn upsert(
+    /// - Returns `Error::Conflict` if the existing record has a different version.
+    /// - Returns Self::Error if a hook fails or the database operation fails.
+    async fn upsert<'e, E>(
         &self,
-        pool: &sqlx::SqlitePool,
+        executor: E,
         skip_cols: &[&str],
         conflict_col: &str,
     ) -> Result<Self, Self::Error>
     where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
         Self: Sized + for<'r> FromRow<'r, SqliteRow> + Serialize + Unpin + Send + Debug,
     {
-        let mut column_names = Vec::new();
-        let mut ordered_vals = Vec::new();
-        let mut qmarks = Vec::new();
-        let mut update_clause = Vec::new();
-        let map = match serde_json::to_value(self)? {
-            serde_json::Value::Object(m) => m,
-            _ => {
-                return Err(serde_json::Error::custom(format!(
-                    "Failed to serialize {:?} into a map while running an upsert query.",
-                    &self
-                )))?
-            }
+        let hooked = before_write(self, Write::Upsert).await?;
+        let model = hooked.as_ref().unwrap_or(self);
+        model.validate()?;
+        let (query_str, vals) = upsert_sql(model, skip_cols, conflict_col)?;
+        let query = bind_values(&query_str, vals);
+        let saved = query.fetch_optional(executor).await.map_err(Error::from)?;
+        // Only a failed version check keeps a conflicting record from being updated
+        let saved: Self = saved.ok_or_else(conflict::<Self>)?;
+        after_write(std::slice::from_ref(&saved), Write::Upsert).await?;
+        Ok(saved)
+    }
+
+    /// Inserts many records into the table and returns the newly created model instances.
+    ///
+    /// The records are written with multi-row `insert` statements, split into chunks so no
+    /// statement exceeds SQLite's bound parameter limit, and all chunks run inside a single
+    /// transaction. If `conn` is already a transaction, a savepoint is used instead.
+    ///
+    /// # Arguments
+    /// - conn: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - models: The model instances to insert.
+    /// - skip_cols: A list of column names to skip during the insertion, as with `insert`.
+    /// Columns marked `skip_insert` in `columns` are always skipped.
+    ///
+    /// Hooks are called for each model as with `insert`.
+    ///
+    /// # Returns
+    /// - Result<Vec<Self>, Self::Error>: Returns the newly inserted model instances on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if a hook fails, the models do not all serialize to the same
+    /// columns or the database operation fails. Nothing is inserted if any chunk fails.
+    async fn insert_many<'a, A>(
+        conn: A,
+        models: &[Self],
+        skip_cols: &[&str],
+    ) -> Result<Vec<Self>, Self::Error>
+    where
+        A: sqlx::Acquire<'a, Database = sqlx::Sqlite> + Send,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Serialize + Unpin + Send + Sync + Debug,
+    {
+        let hooked = before_write_all(models, Write::Insert).await?;
+        let models = hooked.as_deref().unwrap_or(models);
+        for model in models {
+            model.validate()?;
+        }
+        let skip_insert = Self::columns().iter().filter(|c| c.skip_insert);
+        let mut skip: Vec<&str> = skip_insert.map(|c| c.name).collect();
+        skip.extend_from_slice(skip_cols);
+        let saved = bulk_insert(conn, models, &skip, |_| Ok(String::new())).await?;
+        after_write(&saved, Write::Insert).await?;
+        Ok(saved)
+    }
+
+    /// Inserts or updates many records in the table depending on whether a conflict occurs on
+    /// a specific column, and returns the upserted model instances.
+    ///
+    /// Chunking and transaction handling are the same as `insert_many`, except for a model with
+    /// a `version` column, which is written with one statement per model inside the
+    /// transaction, so each existing record is checked against the version of its own model.
+    ///
+    /// # Arguments
+    /// - conn: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - models: The model instances to upsert.
+    /// - skip_cols: A list of column names to skip during the insertion, as with `upsert`.
+    /// - conflict_col: The name of the column to check for conflicts (usually the primary key).
+    ///
+    /// Hooks are called for each model, and soft deleted records restored, as with `upsert`.
+    ///
+    /// # Returns
+    /// - Result<Vec<Self>, Self::Error>: Returns the upserted model instances on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns `Error::Conflict` if an existing record has a different version than its
+    /// model, as with `upsert`.
+    /// - Returns Self::Error if a hook fails, the models do not all serialize to the same
+    /// columns or the database operation fails. Nothing is written if any chunk fails.
+    async fn upsert_many<'a, A>(
+        conn: A,
+        models: &[Self],
+        skip_cols: &[&str],
+        conflict_col: &str,
+    ) -> Result<Vec<Self>, Self::Error>
+    where
+        A: sqlx::Acquire<'a, Database = sqlx::Sqlite> + Send,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Serialize + Unpin + Send + Sync + Debug,
+    {
+        let hooked = before_write_all(models, Write::Upsert).await?;
+        let models = hooked.as_deref().unwrap_or(models);
+        for model in models {
+            model.validate()?;
+        }
+        if version_column::<Self>().is_some_and(|v| !skip_cols.contains(&v)) {
+            let saved = upsert_each(conn, models, skip_cols, conflict_col).await?;
+            after_write(&saved, Write::Upsert).await?;
+            return Ok(saved);
+        }
+        let conflict_col = column_ident::<Self>(conflict_col)?;
+        let restore = match deleted_at::<Self>() {
+            Some((deleted_at, _)) => Some(format!("{} = null", column_ident::<Self>(deleted_at)?)),
+            None => None,
         };
-        for (col, val) in map {
-            if !skip_cols.contains(&col.as_str()) {
-                column_names.push(col.to_string());
-                ordered_vals.push(val);
-                qmarks.push("?");
-                update_clause.push(format!("{} = ?", col));
+        let saved = bulk_insert(conn, models, skip_cols, |column_names| {
+            let update_clause: Vec<String> = column_names
+                .iter()
+                .map(|c| format!("{} = excluded.{}", c, c))
+                .chain(restore.clone())
+                .collect();
+            Ok(format!(
+                " on conflict({}) do update set {}",
+                conflict_col,
+                update_clause.join(",")
+            ))
+        })
+        .await?;
+        after_write(&saved, Write::Upsert).await?;
+        Ok(saved)
+    }
+
+    /// Updates the record whose `key_col` matches the value of `key_col` on `self`, and returns
+    /// the updated model instance.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - key_col: The name of the column used to find the record to update (usually the primary key).
+    /// - only_cols: The columns to update. If `None`, every column except `key_col` is updated.
+    ///
+    /// If the model has `hooks`, `before_update` is called on a copy of the model before it is
+    /// written, and `after_update` with the saved model. As with `upsert`, the `created_at`
+    /// column is never written and the `updated_at` column is always set to the current time.
+    /// If a `version` column is declared, the record is only updated if its version matches the
+    /// model, and its version is incremented. A soft deleted record is not updated, as it is not
+    /// found by `select_one`.
+    ///
+    /// # Returns
+    /// - Result<Self, Self::Error>: Returns the updated model instance on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if a hook fails, if `key_col` or one of `only_cols` is not a column
+    /// of the model, if no columns are left to update, or if the database operation fails.
+    /// - Returns `Error::NotFound` if no record matches `key_col` or it is soft deleted, or
+    /// `Error::Conflict` instead if a `version` column is declared, since a record which was
+    /// deleted and one which was written since it was read cannot be told apart by the statement.
+    async fn update<'e, E>(
+        &self,
+        executor: E,
+        key_col: &str,
+        only_cols: Option<&[&str]>,
+    ) -> Result<Self, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Serialize + Unpin + Send + Debug,
+    {
+        let hooked = before_write(self, Write::Update).await?;
+        let model = hooked.as_ref().unwrap_or(self);
+        model.validate()?;
+        let col_vals = model.column_values().map_err(Error::from)?;
+        if let Some(missing) = only_cols
+            .unwrap_or_default()
+            .iter()
+            .find(|c| !col_vals.iter().any(|(col, _)| col == *c))
+        {
+            return Err(Error::UnknownColumn(missing.to_string()))?;
+        }
+
+        let mut key_val = None;
+        let mut version_val = None;
+        let mut update_clause = Vec::new();
+        let mut touch_clause = Vec::new();
+        let mut ordered_vals = Vec::new();
+        for (col, val) in col_vals {
+            if col == key_col {
+                key_val = Some(BasicType::from_json(Self::columns(), &col, &val)?);
+            } else if version_column::<Self>() == Some(col.as_str()) {
+                version_val = Some(BasicType::from_json(Self::columns(), &col, &val)?);
+                let col = column_ident::<Self>(&col)?;
+                touch_clause.push(format!("{} = {} + 1", col, col));
+            } else if is_updated_at::<Self>(&col) {
+                touch_clause.push(format!(
+                    "{} = {}",
+                    column_ident::<Self>(&col)?,
+                    placeholder::<Self>(&col)
+                ));
+            } else if is_unwritten::<Self>(&col) {
+                continue;
+            } else if only_cols.is_none_or(|only| only.contains(&col.as_str())) {
+                ordered_vals.push(BasicType::from_json(Self::columns(), &col, &val)?);
+                update_clause.push(format!(
+                    "{} = {}",
+                    column_ident::<Self>(&col)?,
+                    placeholder::<Self>(&col)
+                ));
             }
         }
+        let key_val = key_val.ok_or(Error::UnknownColumn(key_col.to_string()))?;
+        if update_clause.is_empty() {
+            return Err(Error::InvalidQuery(format!(
+                "update: no columns of {:?} left to update",
+                &self
+            )))?;
+        }
+        update_clause.extend(touch_clause);
+        ordered_vals.push(key_val);
+        let mut where_clause = format!("{} = ?", column_ident::<Self>(key_col)?);
+        if let (Some(version), Some(val)) = (version_column::<Self>(), version_val.clone()) {
+            where_clause.push_str(&format!(" and {} = ?", column_ident::<Self>(version)?));
+            ordered_vals.push(val);
+        }
+        if let Some((deleted_at, _)) = deleted_at::<Self>() {
+            let deleted_at = column_ident::<Self>(deleted_at)?;
+            where_clause.push_str(&format!(" and {} is null", deleted_at));
+        }
+
         let query_str = format!(
-            "insert into {} ({}) values ({}) on conflict({}) do update set {} returning *;",
-            Self::table_name(),
-            column_names.join(","),
-            qmarks.join(","),
-            conflict_col,
+            "update {} set {} where {} returning *;",
+            table_ident::<Self>()?,
             update_clause.join(","),
+            where_clause,
         );
-
-        let mut vals = Vec::new();
-        for _ in 0..2 {
-            ordered_vals.iter().for_each(|v| vals.push(v.to_owned()));
-        }
-        let query = bind_values(&query_str, &vals).ok_or(serde_json::Error::custom(format!(
-            "Upsert: cannot parse attributes of {:?} into Sqlite compatible types",
-            &self
-        )))?;
-        Ok(query.fetch_one(pool).await?)
+        let query = bind_values(&query_str, ordered_vals);
+        let updated = query.fetch_optional(executor).await.map_err(Error::from)?;
+        let updated: Self = match (updated, version_val) {
+            (Some(updated), _) => updated,
+            (None, Some(_)) => return Err(conflict::<Self>())?,
+            (None, None) => {
+                return Err(Error::NotFound {
+                    table: Self::table_name(),
+                    column: key_col.to_string(),
+                })?
+            }
+        };
+        after_write(std::slice::from_ref(&updated), Write::Update).await?;
+        Ok(updated)
     }
 
     /// Selects a single record from the table based on the specified column and value.
     ///
     /// # Arguments
-    /// - pool: A reference to a sqlx::SqlitePool used for database interaction.
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
     /// - col: The name of the column to filter by.
     /// - val: The value to filter by, wrapped in BasicType.
     ///
@@ -206,35 +849,36 @@ pub trait SqliteModel {
     /// - Result<Self, Self::Error>: Returns the selected model instance on success, otherwise returns an error.
     ///
     /// # Errors
-    /// - Returns Self::Error if the database operation fails or if no record matches the filter
-    /// or some other sqlx::Error occurs.
-    async fn select_one(
-        pool: &sqlx::SqlitePool,
+    /// - Returns `Error::NotFound` if no record matches the filter.
+    /// - Returns Self::Error if the database operation fails.
+    async fn select_one<'e, E>(
+        executor: E,
         col: &str,
         val: serde_json::Value,
     ) -> Result<Self, Self::Error>
     where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
         Self: Sized + for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
     {
+        let filter = scoped::<Self>(&Filter::eq(col, val), Trashed::Without);
+        let (where_clause, vals) = filter.to_sql(Self::columns())?;
         let query_str = format!(
-            "select * from {} where {} = ? limit 1;",
-            Self::table_name(),
-            col
+            "select * from {} where {} limit 1;",
+            table_ident::<Self>()?,
+            where_clause
         );
-        let vals = vec![val];
-        let query = bind_values(&query_str, &vals).ok_or(serde_json::Error::custom(format!(
-            "Select One: cannot parse {} into Sqlite compatible type",
-            &vals.get(0).ok_or(serde_json::Error::custom(
-                "select_one: vec of vals should have exactly 1 item, found none"
-            ))?
-        )))?;
-        Ok(query.fetch_one(pool).await?)
+        let query = bind_values(&query_str, vals);
+        let selected = query.fetch_optional(executor).await.map_err(Error::from)?;
+        Ok(selected.ok_or(Error::NotFound {
+            table: Self::table_name(),
+            column: col.to_string(),
+        })?)
     }
 
     /// Selects multiple records from the table based on the specified column and value.
     ///
     /// # Arguments
-    /// - pool: A reference to a sqlx::SqlitePool used for database interaction.
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
     /// - col: The name of the column to filter by.
     /// - val: The value to filter by, wrapped in BasicType.
     ///
@@ -244,99 +888,602 @@ pub trait SqliteModel {
     ///
     /// # Errors
     /// - Returns Self::Error if the database operation fails.
-    async fn select_many(
-        pool: &sqlx::SqlitePool,
+    async fn select_many<'e, E>(
+        executor: E,
         col: &str,
         val: serde_json::Value,
     ) -> Result<Vec<Self>, Self::Error>
     where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
         Self: Sized + for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
     {
-        let query_str = format!("select * from {} where {} = ?;", Self::table_name(), col);
-        let vals = vec![val];
-        let query = bind_values(&query_str, &vals).ok_or(serde_json::Error::custom(format!(
-            "select_many: cannot parse {} into Sqlite compatible type",
-            &vals.get(0).ok_or(serde_json::Error::custom(
-                "select_many: vec of vals should have exactly 1 item, found none"
-            ))?
-        )))?;
-        Ok(query.fetch_all(pool).await?)
+        Self::select_where(executor, &Filter::eq(col, val)).await
     }
 
-    /// Deletes a single record from the table based on the specified column and value and returns the deleted model instance.
+    /// Selects multiple records from the table based on the specified column and value, with
+    /// ordering, limit and offset applied.
     ///
     /// # Arguments
-    /// - pool: A reference to a sqlx::SqlitePool used for database interaction.
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
     /// - col: The name of the column to filter by.
     /// - val: The value to filter by, wrapped in BasicType.
+    /// - options: The `order by`, `limit` and `offset` of the query.
     ///
     /// # Returns
-    /// - Result<Vec<Self>, Self::Error>: Returns the deleted model instance on success, otherwise returns an error.
+    /// - Result<Vec<Self>, Self::Error>: Returns a vector of model instances that
+    /// match the filter on success, otherwise returns an error.
     ///
     /// # Errors
-    /// - Returns Self::Error if the database operation fails or if no record matches the filter.
-    async fn delete(
-        pool: &sqlx::SqlitePool,
+    /// - Returns Self::Error if the database operation fails.
+    async fn select_many_with<'e, E>(
+        executor: E,
         col: &str,
         val: serde_json::Value,
+        options: &SelectOptions,
     ) -> Result<Vec<Self>, Self::Error>
     where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
         Self: Sized + for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
     {
-        let query_str = format!(
-            "delete from {} where {} = ? returning *;",
-            Self::table_name(),
-            col
-        );
-        let vals = vec![val];
-        let query = bind_values(&query_str, &vals).ok_or(serde_json::Error::custom(format!(
-            "delete: cannot parse {} into Sqlite compatible type",
-            &vals.get(0).ok_or(serde_json::Error::custom(
-                "delete: vec of vals should have extactly 1 item. Found none"
-            ))?
-        )))?;
-        Ok(query.fetch_all(pool).await?)
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use async_trait::async_trait;
-    use serde::Serialize;
-    use sqlx::prelude::FromRow;
-
-    use super::SqliteModel;
-
-    #[derive(Debug)]
-    enum Error {
-        SqlxError(sqlx::Error),
-        SerdeJsonError(serde_json::Error),
+        Self::select_where_with(executor, &Filter::eq(col, val), options).await
     }
 
-    impl std::fmt::Display for Error {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            write!(f, "{}", self)
-        }
+    /// Selects every record from the table, with ordering, limit and offset applied.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - options: The `order by`, `limit` and `offset` of the query.
+    ///
+    /// # Returns
+    /// - Result<Vec<Self>, Self::Error>: Returns a vector of model instances on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if the database operation fails.
+    async fn select_all<'e, E>(
+        executor: E,
+        options: &SelectOptions,
+    ) -> Result<Vec<Self>, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
+    {
+        Self::select_where_with(executor, &Filter::all(), options).await
     }
 
-    impl std::error::Error for Error {}
-
-    impl From<sqlx::Error> for Error {
-        fn from(value: sqlx::Error) -> Self {
-            Error::SqlxError(value)
-        }
+    /// Selects every record from the table which matches `filter`.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - filter: The conditions which each returned record must satisfy.
+    ///
+    /// # Returns
+    /// - Result<Vec<Self>, Self::Error>: Returns a vector of model instances that
+    /// match the filter on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if one of the filter values cannot be bound or the database
+    /// operation fails.
+    async fn select_where<'e, E>(executor: E, filter: &Filter) -> Result<Vec<Self>, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
+    {
+        Self::select_where_with(executor, filter, &SelectOptions::default()).await
     }
 
-    impl From<serde_json::Error> for Error {
-        fn from(value: serde_json::Error) -> Self {
-            Error::SerdeJsonError(value)
-        }
+    /// Selects every record from the table which matches `filter`, with ordering, limit and
+    /// offset applied.
+    ///
+    /// If the model declares a `deleted_at` column, soft deleted records are left out, as they
+    /// are by every other `select_*` method, `paginate`, `count`, `exists` and the aggregates.
+    /// `with_trashed` and `only_trashed` select them.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - filter: The conditions which each returned record must satisfy.
+    /// - options: The `order by`, `limit` and `offset` of the query.
+    ///
+    /// # Returns
+    /// - Result<Vec<Self>, Self::Error>: Returns a vector of model instances that
+    /// match the filter on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if one of the filter values cannot be bound, an `order_by` column
+    /// is invalid, or the database operation fails.
+    async fn select_where_with<'e, E>(
+        executor: E,
+        filter: &Filter,
+        options: &SelectOptions,
+    ) -> Result<Vec<Self>, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
+    {
+        Ok(select_scoped(executor, filter, options, Trashed::Without).await?)
     }
 
-    #[derive(Debug, FromRow, Serialize)]
-    struct TestModel {
-        pub id: i64,
-        pub name: String,
+    /// Selects every record from the table which matches `filter`, including soft deleted
+    /// records, with ordering, limit and offset applied. For a model which is not soft deleted,
+    /// this is the same as `select_where_with`.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - filter: The conditions which each returned record must satisfy.
+    /// - options: The `order by`, `limit` and `offset` of the query.
+    ///
+    /// # Returns
+    /// - Result<Vec<Self>, Self::Error>: Returns a vector of model instances that
+    /// match the filter on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if one of the filter values cannot be bound, an `order_by` column
+    /// is invalid, or the database operation fails.
+    async fn with_trashed<'e, E>(
+        executor: E,
+        filter: &Filter,
+        options: &SelectOptions,
+    ) -> Result<Vec<Self>, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
+    {
+        Ok(select_scoped(executor, filter, options, Trashed::With).await?)
+    }
+
+    /// Selects only the soft deleted records from the table which match `filter`, with
+    /// ordering, limit and offset applied.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - filter: The conditions which each returned record must satisfy.
+    /// - options: The `order by`, `limit` and `offset` of the query.
+    ///
+    /// # Returns
+    /// - Result<Vec<Self>, Self::Error>: Returns a vector of model instances that
+    /// match the filter on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns `Error::InvalidQuery` if the model has no `deleted_at` column.
+    /// - Returns Self::Error if one of the filter values cannot be bound, an `order_by` column
+    /// is invalid, or the database operation fails.
+    async fn only_trashed<'e, E>(
+        executor: E,
+        filter: &Filter,
+        options: &SelectOptions,
+    ) -> Result<Vec<Self>, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Unpin + Send,
+    {
+        require_deleted_at::<Self>("only trashed")?;
+        Ok(select_scoped(executor, filter, options, Trashed::Only).await?)
+    }
+
+    /// Selects one page of records using keyset (cursor) pagination.
+    ///
+    /// Records are ordered by `sort_key`, with the primary key appended as a tie-breaker when it
+    /// differs from `sort_key`. Models without a declared primary key can only be paged by a
+    /// `unique` column. Each page starts strictly after the sort values
+    /// encoded in `cursor`, so pages stay stable while rows are inserted and the query never has
+    /// to skip over earlier rows the way `offset` does.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - sort_key: The column to order by. Defaults to the primary key. It should not contain
+    /// nulls, since nulls never compare greater or less than the cursor.
+    /// - order: The direction to page through the records.
+    /// - page_size: The maximum number of records in the page.
+    /// - cursor: The `next_cursor` of the previous page, or `None` for the first page.
+    ///
+    /// # Returns
+    /// - Result<Page<Self>, Self::Error>: Returns the page of model instances on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns `Error::InvalidParameter` if `page_size` is 0.
+    /// - Returns `Error::InvalidQuery` if no primary key is declared and `sort_key` is not given
+    /// or not a `unique` column.
+    /// - Returns Self::Error if the cursor is malformed, or if the database operation fails.
+    async fn paginate<'e, E>(
+        executor: E,
+        sort_key: Option<&str>,
+        order: Order,
+        page_size: u64,
+        cursor: Option<&str>,
+    ) -> Result<Page<Self>, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Serialize + Unpin + Send + Debug,
+    {
+        let primary_key = Self::primary_key();
+        let sort_key = sort_key.or(primary_key).ok_or(Error::InvalidQuery(format!(
+            "paginate: {} has no primary key, so a sort key is required",
+            Self::table_name()
+        )))?;
+        let mut keys = vec![sort_key];
+        if let Some(pk) = primary_key.filter(|pk| *pk != sort_key) {
+            keys.push(pk);
+        }
+        // Without a tie-breaker, rows sharing the sort value of the cursor would be skipped
+        let unique = Self::columns()
+            .iter()
+            .any(|c| c.name == sort_key && c.unique);
+        if primary_key.is_none() && !unique {
+            return Err(Error::InvalidQuery(format!(
+                "paginate: {} has no primary key to order records with equal {} values by",
+                Self::table_name(),
+                sort_key
+            )))?;
+        }
+
+        if page_size == 0 {
+            return Err(Error::InvalidParameter(
+                "paginate: the page size must be at least 1".to_string(),
+            ))?;
+        }
+        // One extra record tells whether there is another page. SQLite limits are signed, and
+        // no table holds more than `i64::MAX` records
+        let limit = page_size.saturating_add(1).min(i64::MAX as u64);
+        let mut options = SelectOptions::new().limit(limit);
+        for key in &keys {
+            options = options.order_by(key, order);
+        }
+        let scope = scoped::<Self>(&Filter::all(), Trashed::Without);
+        let (scope_clause, mut vals) = scope.to_sql(Self::columns())?;
+        let where_clause = match cursor {
+            Some(cursor) => {
+                let quoted = keys
+                    .iter()
+                    .map(|k| column_ident::<Self>(k))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let op = match order {
+                    Order::Asc => ">",
+                    Order::Desc => "<",
+                };
+                let clause = format!(
+                    "{} and ({}) {} ({})",
+                    scope_clause,
+                    quoted.join(","),
+                    op,
+                    vec!["?"; keys.len()].join(",")
+                );
+                for (val, key) in decode_cursor(cursor, keys.len())?.iter().zip(&keys) {
+                    vals.push(BasicType::from_json(Self::columns(), key, val)?);
+                }
+                clause
+            }
+            None => scope_clause,
+        };
+        let query_str = format!(
+            "select * from {} where {}{};",
+            table_ident::<Self>()?,
+            where_clause,
+            options.to_sql(Self::columns())?,
+        );
+        let query = bind_values(&query_str, vals);
+        let mut items: Vec<Self> = query.fetch_all(executor).await.map_err(Error::from)?;
+
+        let has_more = items.len() as u64 > page_size;
+        items.truncate(page_size as usize);
+        let next_cursor = match items.last().filter(|_| has_more) {
+            Some(last) => {
+                let col_vals = last.column_values().map_err(Error::from)?;
+                let mut cursor_vals = Vec::with_capacity(keys.len());
+                for key in &keys {
+                    let val = col_vals.iter().find(|(col, _)| col == key);
+                    let val = val.ok_or(Error::UnknownColumn(key.to_string()))?;
+                    cursor_vals.push(val.1.to_owned());
+                }
+                Some(encode_cursor(&cursor_vals)?)
+            }
+            None => None,
+        };
+        Ok(Page {
+            items,
+            next_cursor,
+            has_more,
+        })
+    }
+
+    /// Counts the records in the table which match `filter`.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - filter: The conditions which each counted record must satisfy.
+    ///
+    /// # Returns
+    /// - Result<u64, Self::Error>: Returns the number of matching records on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if one of the filter values cannot be bound or the database
+    /// operation fails.
+    async fn count<'e, E>(executor: E, filter: &Filter) -> Result<u64, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        let filter = scoped::<Self>(filter, Trashed::Without);
+        let (where_clause, vals) = filter.to_sql(Self::columns())?;
+        let query_str = format!(
+            "select count(*) from {} where {};",
+            table_ident::<Self>()?,
+            where_clause
+        );
+        let query = bind_values::<(i64,)>(&query_str, vals);
+        let (count,) = query.fetch_one(executor).await.map_err(Error::from)?;
+        Ok(count as u64)
+    }
+
+    /// Checks whether any record in the table matches `filter`, without reading the records.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - filter: The conditions which a record must satisfy.
+    ///
+    /// # Returns
+    /// - Result<bool, Self::Error>: Returns whether a matching record exists on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if one of the filter values cannot be bound or the database
+    /// operation fails.
+    async fn exists<'e, E>(executor: E, filter: &Filter) -> Result<bool, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        let filter = scoped::<Self>(filter, Trashed::Without);
+        let (where_clause, vals) = filter.to_sql(Self::columns())?;
+        let query_str = format!(
+            "select exists (select 1 from {} where {});",
+            table_ident::<Self>()?,
+            where_clause
+        );
+        let query = bind_values::<(bool,)>(&query_str, vals);
+        let (exists,) = query.fetch_one(executor).await.map_err(Error::from)?;
+        Ok(exists)
+    }
+
+    /// Applies an aggregate function to `col` over every record in the table which matches
+    /// `filter`.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - func: The aggregate function to apply.
+    /// - col: The name of the column to aggregate.
+    /// - filter: The conditions which each aggregated record must satisfy.
+    ///
+    /// # Returns
+    /// - Result<BasicType, Self::Error>: Returns the aggregated value on success, otherwise
+    /// returns an error. The value is `BasicType::Null` if no record matches.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if `col` is not a column of the model, one of the filter values
+    /// cannot be bound, or the database operation fails.
+    async fn aggregate<'e, E>(
+        executor: E,
+        func: Aggregate,
+        col: &str,
+        filter: &Filter,
+    ) -> Result<BasicType, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        let filter = scoped::<Self>(filter, Trashed::Without);
+        let (where_clause, vals) = filter.to_sql(Self::columns())?;
+        let query_str = format!(
+            "select {}({}) from {} where {};",
+            func.as_sql(),
+            column_ident::<Self>(col)?,
+            table_ident::<Self>()?,
+            where_clause
+        );
+        let query = bind_values::<BasicRow>(&query_str, vals);
+        let row = query.fetch_one(executor).await.map_err(Error::from)?;
+        Ok(row.0.into_iter().next().unwrap_or(BasicType::Null))
+    }
+
+    /// Applies an aggregate function to `col` over every record in the table which matches
+    /// `filter`, once for each distinct value of `group_by`.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - func: The aggregate function to apply.
+    /// - col: The name of the column to aggregate.
+    /// - group_by: The name of the column to group the records by.
+    /// - filter: The conditions which each aggregated record must satisfy.
+    ///
+    /// # Returns
+    /// - Result<Vec<(BasicType, BasicType)>, Self::Error>: Returns each value of `group_by`
+    /// paired with the aggregated value of its group, ordered by `group_by`, on success,
+    /// otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if `col` or `group_by` is not a column of the model, one of the
+    /// filter values cannot be bound, or the database operation fails.
+    async fn aggregate_by<'e, E>(
+        executor: E,
+        func: Aggregate,
+        col: &str,
+        group_by: &str,
+        filter: &Filter,
+    ) -> Result<Vec<(BasicType, BasicType)>, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        let filter = scoped::<Self>(filter, Trashed::Without);
+        let (where_clause, vals) = filter.to_sql(Self::columns())?;
+        let group_by = column_ident::<Self>(group_by)?;
+        let query_str = format!(
+            "select {}, {}({}) from {} where {} group by {} order by {};",
+            group_by,
+            func.as_sql(),
+            column_ident::<Self>(col)?,
+            table_ident::<Self>()?,
+            where_clause,
+            group_by,
+            group_by,
+        );
+        let query = bind_values::<BasicRow>(&query_str, vals);
+        let rows = query.fetch_all(executor).await.map_err(Error::from)?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let mut vals = row.0.into_iter();
+                let key = vals.next().unwrap_or(BasicType::Null);
+                (key, vals.next().unwrap_or(BasicType::Null))
+            })
+            .collect())
+    }
+
+    /// Deletes a single record from the table based on the specified column and value and returns the deleted model instance.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - col: The name of the column to filter by.
+    /// - val: The value to filter by, wrapped in BasicType.
+    ///
+    /// # Returns
+    /// - Result<Vec<Self>, Self::Error>: Returns the deleted model instance on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if a hook fails, the database operation fails or if no record
+    /// matches the filter.
+    async fn delete<'e, E>(
+        executor: E,
+        col: &str,
+        val: serde_json::Value,
+    ) -> Result<Vec<Self>, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Unpin + Send + Sync,
+    {
+        Self::delete_where(executor, &Filter::eq(col, val)).await
+    }
+
+    /// Deletes every record from the table which matches `filter` and returns the deleted model
+    /// instances.
+    ///
+    /// If the model declares a `deleted_at` column, the records are soft deleted instead: the
+    /// column is set to the current time, and records which were already soft deleted are left
+    /// alone. `force_delete` removes records for good.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - filter: The conditions which each deleted record must satisfy.
+    ///
+    /// If the model has `hooks`, `before_delete` is called with `filter` before anything is
+    /// deleted, and `after_delete` with each deleted model.
+    ///
+    /// # Returns
+    /// - Result<Vec<Self>, Self::Error>: Returns the deleted model instances on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if a hook fails, one of the filter values cannot be bound or the
+    /// database operation fails.
+    async fn delete_where<'e, E>(executor: E, filter: &Filter) -> Result<Vec<Self>, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Unpin + Send + Sync,
+    {
+        let Some((deleted_at, format)) = deleted_at::<Self>() else {
+            return Self::force_delete(executor, filter).await;
+        };
+        before_where::<Self>(filter, Write::Delete).await?;
+        let scope = scoped::<Self>(filter, Trashed::Without);
+        let (where_clause, vals) = scope.to_sql(Self::columns())?;
+        let query_str = format!(
+            "update {} set {} = {} where {} returning *;",
+            table_ident::<Self>()?,
+            column_ident::<Self>(deleted_at)?,
+            format.now_sql(),
+            where_clause
+        );
+        let query = bind_values(&query_str, vals);
+        let deleted: Vec<Self> = query.fetch_all(executor).await.map_err(Error::from)?;
+        after_write(&deleted, Write::Delete).await?;
+        Ok(deleted)
+    }
+
+    /// Removes every record from the table which matches `filter`, including soft deleted
+    /// records, and returns the removed model instances. For a model which is not soft deleted,
+    /// this is the same as `delete_where`.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - filter: The conditions which each removed record must satisfy.
+    ///
+    /// Hooks are called as with `delete_where`.
+    ///
+    /// # Returns
+    /// - Result<Vec<Self>, Self::Error>: Returns the removed model instances on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns Self::Error if a hook fails, one of the filter values cannot be bound or the
+    /// database operation fails.
+    async fn force_delete<'e, E>(executor: E, filter: &Filter) -> Result<Vec<Self>, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Unpin + Send + Sync,
+    {
+        before_where::<Self>(filter, Write::Delete).await?;
+        let deleted: Vec<Self> = hard_delete(executor, filter).await?;
+        after_write(&deleted, Write::Delete).await?;
+        Ok(deleted)
+    }
+
+    /// Restores every soft deleted record from the table which matches `filter`, by setting
+    /// its `deleted_at` column back to `NULL`, and returns the restored model instances.
+    ///
+    /// # Arguments
+    /// - executor: The sqlx::SqlitePool, connection or transaction used for database interaction.
+    /// - filter: The conditions which each restored record must satisfy.
+    ///
+    /// If the model has `hooks`, `before_restore` is called with `filter` before anything is
+    /// restored, and `after_restore` with each restored model.
+    ///
+    /// # Returns
+    /// - Result<Vec<Self>, Self::Error>: Returns the restored model instances on success, otherwise returns an error.
+    ///
+    /// # Errors
+    /// - Returns `Error::InvalidQuery` if the model has no `deleted_at` column.
+    /// - Returns Self::Error if a hook fails, one of the filter values cannot be bound or the
+    /// database operation fails.
+    async fn restore<'e, E>(executor: E, filter: &Filter) -> Result<Vec<Self>, Self::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+        Self: Sized + for<'r> FromRow<'r, SqliteRow> + Unpin + Send + Sync,
+    {
+        let (deleted_at, _) = require_deleted_at::<Self>("restore")?;
+        before_where::<Self>(filter, Write::Restore).await?;
+        let scope = scoped::<Self>(filter, Trashed::Only);
+        let (where_clause, vals) = scope.to_sql(Self::columns())?;
+        let query_str = format!(
+            "update {} set {} = null where {} returning *;",
+            table_ident::<Self>()?,
+            column_ident::<Self>(deleted_at)?,
+            where_clause
+        );
+        let query = bind_values(&query_str, vals);
+        let restored: Vec<Self> = query.fetch_all(executor).await.map_err(Error::from)?;
+        after_write(&restored, Write::Restore).await?;
+        Ok(restored)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::get_first, clippy::useless_format)]
+mod tests {
+    use async_trait::async_trait;
+    use serde::Serialize;
+    use sqlx::prelude::FromRow;
+
+    use super::SqliteModel;
+    use crate::{
+        Affinity, Aggregate, BasicType, Column, ConstraintKind, Error, Filter, IfNotExists, Order,
+        SelectOptions, TimeFormat, Timestamp,
+    };
+
+    #[derive(Debug, FromRow, Serialize)]
+    struct TestModel {
+        pub id: i64,
+        pub name: String,
         pub passwd: Vec<u8>,
         pub created_at: i64,
     }
@@ -577,4 +1724,1284 @@ mod tests {
             .unwrap();
         assert_eq!(res.len(), 0);
     }
+
+    async fn insert_named(pool: &sqlx::SqlitePool, names: &[&str]) {
+        for (i, name) in names.iter().enumerate() {
+            let test = TestModel {
+                id: 0,
+                name: name.to_string(),
+                passwd: vec![i as u8],
+                created_at: i as i64,
+            };
+            test.insert(pool, &["id"]).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_select_where() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        create_table(&pool).await.unwrap();
+        insert_named(&pool, &["alice", "bob", "carol", "dave", "anna"]).await;
+
+        let res = TestModel::select_where(&pool, &Filter::ge("created_at", 3))
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].name, "dave");
+        assert_eq!(res[1].name, "anna");
+
+        let filter = Filter::like("name", "a%").or(Filter::is_in("id", [2, 3]));
+        let res = TestModel::select_where(&pool, &filter).await.unwrap();
+        let names: Vec<&str> = res.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["alice", "bob", "carol", "anna"]);
+
+        let filter = Filter::between("id", 2, 4).and(!Filter::eq("name", "carol"));
+        let res = TestModel::select_where(&pool, &filter).await.unwrap();
+        let names: Vec<&str> = res.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["bob", "dave"]);
+
+        let res = TestModel::select_where(&pool, &Filter::is_null("name"))
+            .await
+            .unwrap();
+        assert!(res.is_empty());
+
+        let res = TestModel::select_where(&pool, &Filter::all())
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_delete_where() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        create_table(&pool).await.unwrap();
+        insert_named(&pool, &["alice", "bob", "carol", "dave", "anna"]).await;
+
+        let filter = Filter::lt("id", 3).or(Filter::eq("name", "anna"));
+        let res = TestModel::delete_where(&pool, &filter).await.unwrap();
+        let names: Vec<&str> = res.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["alice", "bob", "anna"]);
+
+        let res: Vec<TestModel> = sqlx::query_as("select * from TestModel")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        let names: Vec<&str> = res.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["carol", "dave"]);
+    }
+
+    #[derive(Debug, Serialize, crate::SqliteModel)]
+    #[model(table = "users")]
+    struct DerivedModel {
+        #[model(primary_key, skip_insert)]
+        pub id: i64,
+        #[model(rename = "user_name", unique)]
+        pub name: String,
+        pub email: Option<String>,
+    }
+
+    #[test]
+    fn test_derive_metadata() {
+        assert_eq!(DerivedModel::table_name(), "users");
+        assert_eq!(DerivedModel::primary_key(), Some("id"));
+        assert_eq!(
+            DerivedModel::columns(),
+            &[
+                Column::new("id")
+                    .primary_key()
+                    .skip_insert()
+                    .affinity(Affinity::Integer)
+                    .not_null(),
+                Column::new("user_name")
+                    .field("name")
+                    .affinity(Affinity::Text)
+                    .not_null()
+                    .unique(),
+                Column::new("email").affinity(Affinity::Text),
+            ]
+        );
+        assert_eq!(TestModel::columns(), &[]);
+        assert_eq!(TestModel::primary_key(), None);
+    }
+
+    #[derive(Debug, Serialize, crate::SqliteModel)]
+    struct Event {
+        #[model(primary_key, skip_insert)]
+        id: i64,
+        #[model(default = "'pending'")]
+        status: String,
+        score: Option<f64>,
+        #[model(affinity = "numeric", default = "unixepoch()")]
+        created_at: i64,
+        payload: Vec<u8>,
+    }
+
+    #[tokio::test]
+    async fn test_derive_create_table() {
+        assert_eq!(
+            Event::ddl(IfNotExists(true)).unwrap(),
+            r#"create table if not exists "Event" (
+    "id" integer primary key not null,
+    "status" text not null default 'pending',
+    "score" real,
+    "created_at" numeric not null default (unixepoch()),
+    "payload" blob not null
+);"#
+        );
+        assert!(matches!(
+            TestModel::ddl(IfNotExists(true)),
+            Err(Error::InvalidQuery(_))
+        ));
+
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        Event::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        Event::create_table(&pool, IfNotExists(true)).await.unwrap();
+        assert!(Event::create_table(&pool, IfNotExists(false))
+            .await
+            .is_err());
+
+        sqlx::query("insert into Event (payload) values (x'00')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let (status, created_at): (String, i64) =
+            sqlx::query_as("select status, created_at from Event where id = 1")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(status, "pending");
+        assert!(created_at > 0);
+
+        DerivedModel::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        let user = DerivedModel {
+            id: 0,
+            name: "alice".to_string(),
+            email: None,
+        };
+        user.insert(&pool, &[]).await.unwrap();
+        let res = user.insert(&pool, &[]).await;
+        assert!(matches!(
+            res,
+            Err(Error::ConstraintViolation {
+                kind: ConstraintKind::Unique,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_derive_insert_select() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        DerivedModel::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        let model = DerivedModel {
+            id: 42,
+            name: "alice".to_string(),
+            email: None,
+        };
+
+        let res = model.insert(&pool, &[]).await.unwrap();
+        assert_eq!(res.id, 1);
+        assert_eq!(res.name, "alice");
+        assert_eq!(res.email, None);
+
+        let model = DerivedModel {
+            id: 1,
+            name: "alice".to_string(),
+            email: Some("alice@example.com".to_string()),
+        };
+        model.upsert(&pool, &[], "user_name").await.unwrap();
+
+        let res = DerivedModel::select_one(&pool, "user_name", "alice".into())
+            .await
+            .unwrap();
+        assert_eq!(res.id, 1);
+        assert_eq!(res.email.as_deref(), Some("alice@example.com"));
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, serde::Deserialize)]
+    struct Address {
+        street: String,
+        zip: Option<String>,
+    }
+
+    #[derive(Debug, Serialize, crate::SqliteModel)]
+    struct Profile {
+        #[model(primary_key)]
+        id: i64,
+        #[model(json)]
+        tags: Vec<String>,
+        #[model(json)]
+        settings: std::collections::HashMap<String, i64>,
+        #[model(json)]
+        address: Option<Address>,
+        #[model(json)]
+        history: Vec<u8>,
+        avatar: Vec<u8>,
+    }
+
+    #[tokio::test]
+    async fn test_json_columns() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        Profile::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+
+        let profile = Profile {
+            id: 1,
+            tags: vec!["admin".to_string(), "ops".to_string()],
+            settings: [("volume".to_string(), 11)].into_iter().collect(),
+            address: Some(Address {
+                street: "1 Main St".to_string(),
+                zip: None,
+            }),
+            history: vec![1, 2],
+            avatar: vec![0, 255],
+        };
+        let res = profile.insert(&pool, &[]).await.unwrap();
+        assert_eq!(res.tags, profile.tags);
+        assert_eq!(res.settings, profile.settings);
+        assert_eq!(res.address, profile.address);
+        assert_eq!(res.history, profile.history);
+        assert_eq!(res.avatar, profile.avatar);
+
+        let (tags, history, avatar): (String, String, String) = sqlx::query_as(
+            "select typeof(tags), history, typeof(avatar) from Profile where json_valid(tags)",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(tags, "text");
+        assert_eq!(history, "[1,2]");
+        assert_eq!(avatar, "blob");
+
+        let profile = Profile {
+            address: None,
+            ..res
+        };
+        let res = profile.update(&pool, "id", None).await.unwrap();
+        assert_eq!(res.address, None);
+
+        let res = Profile::select_one(&pool, "tags", serde_json::json!(["admin", "ops"]))
+            .await
+            .unwrap();
+        assert_eq!(res.id, 1);
+
+        let err = sqlx::query("insert into Profile values (2, json(?), '{}', null, '[]', x'')")
+            .bind("not json")
+            .execute(&pool)
+            .await;
+        assert!(err.is_err());
+    }
+
+    #[derive(Debug, Serialize, crate::SqliteModel)]
+    #[model(table = "scores")]
+    struct Scores {
+        #[model(primary_key)]
+        id: i64,
+        points: sqlx::types::Json<Vec<i64>>,
+    }
+
+    #[tokio::test]
+    async fn test_integer_arrays() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        Scores::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        for (id, points) in [(1, vec![1, 2]), (2, vec![]), (3, vec![1, 300])] {
+            let scores = Scores {
+                id,
+                points: sqlx::types::Json(points.clone()),
+            };
+            let res = scores.insert(&pool, &[]).await.unwrap();
+            assert_eq!(res.points.0, points);
+            let res = Scores::select_one(&pool, "id", id.into()).await.unwrap();
+            assert_eq!(res.points.0, points);
+        }
+        let types: Vec<(String,)> = sqlx::query_as("select distinct typeof(points) from scores")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(types, [("text".to_string(),)]);
+    }
+
+    #[tokio::test]
+    async fn test_update() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        create_table(&pool).await.unwrap();
+        insert_named(&pool, &["alice", "bob"]).await;
+
+        let test = TestModel {
+            id: 2,
+            name: "robert".to_string(),
+            passwd: vec![9, 9],
+            created_at: 100,
+        };
+        let res = test.update(&pool, "id", None).await.unwrap();
+        assert_eq!(res.id, 2);
+        assert_eq!(res.name, "robert");
+        assert_eq!(res.passwd, vec![9, 9]);
+        assert_eq!(res.created_at, 100);
+
+        let test = TestModel {
+            id: 1,
+            name: "alicia".to_string(),
+            passwd: vec![7],
+            created_at: 200,
+        };
+        let res = test.update(&pool, "id", Some(&["name"])).await.unwrap();
+        assert_eq!(res.id, 1);
+        assert_eq!(res.name, "alicia");
+        assert_eq!(res.passwd, vec![0]);
+        assert_eq!(res.created_at, 0);
+
+        let test = TestModel {
+            id: 3,
+            name: "nobody".to_string(),
+            passwd: vec![],
+            created_at: 0,
+        };
+        let res = test.update(&pool, "id", None).await;
+        assert!(matches!(
+            res,
+            Err(Error::NotFound { table, column }) if table == "TestModel" && column == "id"
+        ));
+
+        let res = test.update(&pool, "id", Some(&["missing"])).await;
+        assert!(matches!(res, Err(Error::UnknownColumn(_))));
+        let res = test.update(&pool, "id", Some(&[])).await;
+        assert!(matches!(res, Err(Error::InvalidQuery(_))));
+
+        let res: Vec<TestModel> = sqlx::query_as("select * from TestModel")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_identifiers() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        create_table(&pool).await.unwrap();
+        DerivedModel::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        insert_named(&pool, &["alice", "bob"]).await;
+
+        let res = TestModel::select_many(&pool, "1 = 1 or name", "x".into()).await;
+        assert!(matches!(res, Err(Error::InvalidIdentifier(_))));
+        let res = TestModel::delete(&pool, "id = id; --", 1.into()).await;
+        assert!(matches!(res, Err(Error::InvalidIdentifier(_))));
+
+        let test = TestModel {
+            id: 1,
+            name: "alice".to_string(),
+            passwd: vec![],
+            created_at: 0,
+        };
+        let res = test.upsert(&pool, &[], "id) do nothing; --").await;
+        assert!(matches!(res, Err(Error::InvalidIdentifier(_))));
+        let res = test.update(&pool, "id or 1 = 1", None).await;
+        assert!(matches!(res, Err(Error::UnknownColumn(_))));
+
+        // Well formed names must still be columns of derived models
+        let res = DerivedModel::select_many(&pool, "name", "alice".into()).await;
+        assert!(matches!(res, Err(Error::UnknownColumn(_))));
+        let res = DerivedModel::select_many(&pool, "user_name", "alice".into()).await;
+        assert!(res.unwrap().is_empty());
+
+        let res: Vec<TestModel> = sqlx::query_as("select * from TestModel")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_rollback() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        create_table(&pool).await.unwrap();
+        DerivedModel::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        let test = TestModel {
+            id: 0,
+            name: "alice".to_string(),
+            passwd: vec![1],
+            created_at: 1,
+        };
+        let user = DerivedModel {
+            id: 0,
+            name: "alice".to_string(),
+            email: None,
+        };
+
+        let mut tx = pool.begin().await.unwrap();
+        let res = test.insert(&mut *tx, &["id"]).await.unwrap();
+        assert_eq!(res.id, 1);
+        user.insert(&mut *tx, &[]).await.unwrap();
+        let res = TestModel::select_many(&mut *tx, "name", "alice".into())
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 1);
+        tx.rollback().await.unwrap();
+
+        let res = TestModel::select_where(&pool, &Filter::all())
+            .await
+            .unwrap();
+        assert!(res.is_empty());
+        let res = DerivedModel::select_where(&pool, &Filter::all())
+            .await
+            .unwrap();
+        assert!(res.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_commit() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        create_table(&pool).await.unwrap();
+        let test = TestModel {
+            id: 0,
+            name: "alice".to_string(),
+            passwd: vec![1],
+            created_at: 1,
+        };
+
+        let mut tx = pool.begin().await.unwrap();
+        test.insert(&mut *tx, &["id"]).await.unwrap();
+        let test = TestModel {
+            id: 1,
+            name: "alicia".to_string(),
+            passwd: vec![2],
+            created_at: 2,
+        };
+        test.update(&mut *tx, "id", Some(&["name"])).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        let res = TestModel::select_one(&mut *conn, "id", 1.into())
+            .await
+            .unwrap();
+        assert_eq!(res.name, "alicia");
+        assert_eq!(res.passwd, vec![1]);
+    }
+
+    fn named_models(names: &[&str]) -> Vec<TestModel> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| TestModel {
+                id: i as i64 + 1,
+                name: name.to_string(),
+                passwd: vec![i as u8],
+                created_at: i as i64,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_insert_many() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        create_table(&pool).await.unwrap();
+
+        let res = TestModel::insert_many(&pool, &[], &["id"]).await.unwrap();
+        assert!(res.is_empty());
+
+        let models = named_models(&["alice", "bob", "carol"]);
+        let res = TestModel::insert_many(&pool, &models, &["id"])
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 3);
+        for (res, model) in res.iter().zip(&models) {
+            assert_eq!(res.id, model.id);
+            assert_eq!(res.name, model.name);
+            assert_eq!(res.passwd, model.passwd);
+            assert_eq!(res.created_at, model.created_at);
+        }
+
+        // 4 columns per row needs several chunks to stay under the bound parameter limit
+        let models: Vec<TestModel> = (0..20000)
+            .map(|i| TestModel {
+                id: i,
+                name: format!("user{}", i),
+                passwd: vec![],
+                created_at: i,
+            })
+            .collect();
+        let res = TestModel::insert_many(&pool, &models, &["id"])
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 20000);
+        assert_eq!(res.last().unwrap().id, 20003);
+        assert_eq!(res.last().unwrap().name, "user19999");
+    }
+
+    #[tokio::test]
+    async fn test_insert_many_rolls_back() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        DerivedModel::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        let models = vec![
+            DerivedModel {
+                id: 0,
+                name: "alice".to_string(),
+                email: None,
+            },
+            DerivedModel {
+                id: 0,
+                name: "alice".to_string(),
+                email: None,
+            },
+        ];
+
+        let res = DerivedModel::insert_many(&pool, &models, &[]).await;
+        assert!(matches!(
+            res,
+            Err(Error::ConstraintViolation {
+                kind: ConstraintKind::Unique,
+                ..
+            })
+        ));
+        let res = DerivedModel::select_where(&pool, &Filter::all())
+            .await
+            .unwrap();
+        assert!(res.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_upsert_many() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        create_table(&pool).await.unwrap();
+        let models = named_models(&["alice", "bob"]);
+        TestModel::insert_many(&pool, &models, &[]).await.unwrap();
+
+        let mut models = named_models(&["alicia", "robert", "carol"]);
+        models[0].passwd = vec![7, 7];
+        let mut tx = pool.begin().await.unwrap();
+        let res = TestModel::upsert_many(&mut *tx, &models, &[], "id")
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        assert_eq!(res.len(), 3);
+
+        let res = TestModel::select_where(&pool, &Filter::all())
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 3);
+        for (res, model) in res.iter().zip(&models) {
+            assert_eq!(res.id, model.id);
+            assert_eq!(res.name, model.name);
+            assert_eq!(res.passwd, model.passwd);
+            assert_eq!(res.created_at, model.created_at);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_select_with_options() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        create_table(&pool).await.unwrap();
+        let mut models = named_models(&["alice", "bob", "carol", "dave"]);
+        models[3].name = "alice".to_string();
+        TestModel::insert_many(&pool, &models, &[]).await.unwrap();
+
+        let options = SelectOptions::new().order_by("id", Order::Desc);
+        let res = TestModel::select_many_with(&pool, "name", "alice".into(), &options)
+            .await
+            .unwrap();
+        let ids: Vec<i64> = res.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![4, 1]);
+
+        let options = SelectOptions::new()
+            .order_by("name", Order::Asc)
+            .order_by("id", Order::Desc)
+            .limit(2)
+            .offset(1);
+        let res = TestModel::select_all(&pool, &options).await.unwrap();
+        let ids: Vec<i64> = res.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        let options = SelectOptions::new().offset(3);
+        let res = TestModel::select_all(&pool, &options).await.unwrap();
+        assert_eq!(res.len(), 1);
+
+        let options = SelectOptions::new().order_by("name desc, id", Order::Asc);
+        let res = TestModel::select_all(&pool, &options).await;
+        assert!(matches!(res, Err(Error::InvalidIdentifier(_))));
+    }
+
+    #[tokio::test]
+    async fn test_paginate() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        DerivedModel::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        let models: Vec<DerivedModel> = ["carol", "alice", "erin", "bob", "dave"]
+            .iter()
+            .map(|name| DerivedModel {
+                id: 0,
+                name: name.to_string(),
+                email: None,
+            })
+            .collect();
+        DerivedModel::insert_many(&pool, &models, &[])
+            .await
+            .unwrap();
+
+        let page = DerivedModel::paginate(&pool, None, Order::Asc, 2, None)
+            .await
+            .unwrap();
+        assert_eq!(page.items.iter().map(|m| m.id).collect::<Vec<_>>(), [1, 2]);
+        assert!(page.has_more);
+
+        let cursor = page.next_cursor.unwrap();
+        let page = DerivedModel::paginate(&pool, None, Order::Asc, 2, Some(&cursor))
+            .await
+            .unwrap();
+        assert_eq!(page.items.iter().map(|m| m.id).collect::<Vec<_>>(), [3, 4]);
+        assert!(page.has_more);
+
+        let cursor = page.next_cursor.unwrap();
+        let page = DerivedModel::paginate(&pool, None, Order::Asc, 2, Some(&cursor))
+            .await
+            .unwrap();
+        assert_eq!(page.items.iter().map(|m| m.id).collect::<Vec<_>>(), [5]);
+        assert!(!page.has_more);
+        assert_eq!(page.next_cursor, None);
+
+        let mut names = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page =
+                DerivedModel::paginate(&pool, Some("user_name"), Order::Desc, 2, cursor.as_deref())
+                    .await
+                    .unwrap();
+            names.extend(page.items.into_iter().map(|m| m.name));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(names, ["erin", "dave", "carol", "bob", "alice"]);
+
+        let page = DerivedModel::paginate(&pool, None, Order::Asc, u64::MAX, None)
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 5);
+        assert!(!page.has_more);
+        assert_eq!(page.next_cursor, None);
+        let res = DerivedModel::paginate(&pool, None, Order::Asc, 0, None).await;
+        assert!(matches!(res, Err(Error::InvalidParameter(_))));
+
+        let res = DerivedModel::paginate(&pool, None, Order::Asc, 2, Some("garbage")).await;
+        assert!(matches!(res, Err(Error::InvalidCursor(_))));
+        let res = TestModel::paginate(&pool, None, Order::Asc, 2, None).await;
+        assert!(matches!(res, Err(Error::InvalidQuery(_))));
+        let res = TestModel::paginate(&pool, Some("name"), Order::Asc, 2, None).await;
+        assert!(matches!(res, Err(Error::InvalidQuery(_))));
+    }
+
+    #[tokio::test]
+    async fn test_paginate_duplicate_sort_values() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        DerivedModel::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        let models: Vec<DerivedModel> = ["a", "b", "c", "d"]
+            .iter()
+            .zip(["x", "y", "y", "z"])
+            .map(|(name, email)| DerivedModel {
+                id: 0,
+                name: name.to_string(),
+                email: Some(email.to_string()),
+            })
+            .collect();
+        DerivedModel::insert_many(&pool, &models, &[])
+            .await
+            .unwrap();
+
+        let mut names = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page =
+                DerivedModel::paginate(&pool, Some("email"), Order::Asc, 2, cursor.as_deref())
+                    .await
+                    .unwrap();
+            names.extend(page.items.into_iter().map(|m| m.name));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(names, ["a", "b", "c", "d"]);
+    }
+
+    #[tokio::test]
+    async fn test_count_and_aggregates() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        create_table(&pool).await.unwrap();
+        insert_named(&pool, &["alice", "bob", "carol", "dave", "anna"]).await;
+
+        assert_eq!(TestModel::count(&pool, &Filter::all()).await.unwrap(), 5);
+        let filter = Filter::like("name", "a%");
+        assert_eq!(TestModel::count(&pool, &filter).await.unwrap(), 2);
+        assert!(TestModel::exists(&pool, &filter).await.unwrap());
+        let filter = Filter::eq("name", "erin");
+        assert!(!TestModel::exists(&pool, &filter).await.unwrap());
+
+        let sum = TestModel::aggregate(&pool, Aggregate::Sum, "created_at", &Filter::all())
+            .await
+            .unwrap();
+        assert_eq!(sum, BasicType::Integer(10));
+        let avg = TestModel::aggregate(&pool, Aggregate::Avg, "id", &Filter::gt("id", 3))
+            .await
+            .unwrap();
+        assert_eq!(avg, BasicType::Real(4.5));
+        let max = TestModel::aggregate(&pool, Aggregate::Max, "name", &Filter::all())
+            .await
+            .unwrap();
+        assert_eq!(max, BasicType::Text("dave".to_string()));
+        let min = TestModel::aggregate(&pool, Aggregate::Min, "id", &Filter::gt("id", 5))
+            .await
+            .unwrap();
+        assert_eq!(min, BasicType::Null);
+
+        TestModel {
+            id: 0,
+            name: "bob".to_string(),
+            passwd: vec![],
+            created_at: 7,
+        }
+        .insert(&pool, &["id"])
+        .await
+        .unwrap();
+        let groups = TestModel::aggregate_by(
+            &pool,
+            Aggregate::Sum,
+            "created_at",
+            "name",
+            &Filter::lt("name", "c"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            groups,
+            vec![
+                (BasicType::from("alice"), BasicType::Integer(0)),
+                (BasicType::from("anna"), BasicType::Integer(4)),
+                (BasicType::from("bob"), BasicType::Integer(8)),
+            ]
+        );
+
+        let res = DerivedModel::aggregate(&pool, Aggregate::Sum, "name", &Filter::all()).await;
+        assert!(matches!(res, Err(Error::UnknownColumn(_))));
+        let res = TestModel::count(&pool, &Filter::eq("1 = 1 or id", 1)).await;
+        assert!(matches!(res, Err(Error::InvalidIdentifier(_))));
+    }
+
+    #[derive(Debug, Clone, Serialize, crate::SqliteModel)]
+    #[model(table = "hooked", hooks)]
+    struct Hooked {
+        #[model(primary_key, skip_insert)]
+        id: i64,
+        email: String,
+        revision: i64,
+    }
+
+    static DELETED: std::sync::atomic::AtomicI64 = std::sync::atomic::AtomicI64::new(0);
+
+    #[async_trait]
+    impl crate::ModelHooks for Hooked {
+        async fn before_insert(&mut self) -> Result<(), Error> {
+            if self.email.is_empty() {
+                return Err(Error::InvalidQuery("email is required".to_string()));
+            }
+            self.email = self.email.to_lowercase();
+            Ok(())
+        }
+
+        async fn before_update(&mut self) -> Result<(), Error> {
+            self.revision += 1;
+            Ok(())
+        }
+
+        async fn before_upsert(&mut self) -> Result<(), Error> {
+            self.revision = 100;
+            Ok(())
+        }
+
+        async fn before_delete(filter: &Filter) -> Result<(), Error> {
+            match filter == &Filter::all() {
+                true => Err(Error::InvalidQuery(
+                    "refusing to delete every row".to_string(),
+                )),
+                false => Ok(()),
+            }
+        }
+
+        async fn after_delete(&self) -> Result<(), Error> {
+            DELETED.fetch_add(self.id, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        Hooked::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        let model = Hooked {
+            id: 0,
+            email: "Alice@Example.com".to_string(),
+            revision: 0,
+        };
+        let saved = model.insert(&pool, &[]).await.unwrap();
+        assert_eq!(saved.email, "alice@example.com");
+        assert_eq!(model.email, "Alice@Example.com");
+
+        let empty = Hooked {
+            email: String::new(),
+            ..model.clone()
+        };
+        assert!(matches!(
+            empty.insert(&pool, &[]).await,
+            Err(Error::InvalidQuery(_))
+        ));
+        let res = Hooked::insert_many(&pool, &[model.clone(), empty], &[]).await;
+        assert!(matches!(res, Err(Error::InvalidQuery(_))));
+        assert_eq!(Hooked::count(&pool, &Filter::all()).await.unwrap(), 1);
+
+        let updated = saved.update(&pool, "id", None).await.unwrap();
+        assert_eq!(updated.revision, 1);
+        let upserted = updated.upsert(&pool, &[], "id").await.unwrap();
+        assert_eq!(upserted.revision, 100);
+
+        let res = Hooked::delete_where(&pool, &Filter::all()).await;
+        assert!(matches!(res, Err(Error::InvalidQuery(_))));
+        assert_eq!(Hooked::count(&pool, &Filter::all()).await.unwrap(), 1);
+        Hooked::delete(&pool, "id", 1.into()).await.unwrap();
+        assert_eq!(DELETED.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    fn not_reserved(val: &serde_json::Value) -> Result<(), String> {
+        match val.as_str() {
+            Some("admin") => Err("is reserved".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, crate::SqliteModel)]
+    #[model(table = "accounts")]
+    struct Account {
+        #[model(primary_key, skip_insert)]
+        id: i64,
+        #[model(rename = "login", validate(non_empty, length(max = 8), custom = not_reserved))]
+        name: String,
+        #[model(validate(email))]
+        email: Option<String>,
+        #[model(validate(range(min = 0, max = 150)))]
+        age: i64,
+        #[model(validate(regex = "^[A-Z]{2}$"))]
+        country: String,
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        Account::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        assert_eq!(Account::rules().len(), 6);
+        let account = Account {
+            id: 0,
+            name: "alice".to_string(),
+            email: None,
+            age: 30,
+            country: "NZ".to_string(),
+        };
+        let saved = account.insert(&pool, &[]).await.unwrap();
+
+        let invalid = Account {
+            name: "administrator".to_string(),
+            email: Some("alice".to_string()),
+            age: -1,
+            country: "nz".to_string(),
+            ..account.clone()
+        };
+        match invalid.insert(&pool, &[]).await {
+            Err(Error::Validation(errors)) => {
+                let fields: Vec<(&str, &str)> =
+                    errors.iter().map(|e| (e.field.as_str(), e.code)).collect();
+                assert_eq!(
+                    fields,
+                    [
+                        ("name", "length"),
+                        ("email", "email"),
+                        ("age", "range"),
+                        ("country", "regex"),
+                    ]
+                );
+            }
+            res => panic!("expected a validation error, found {:?}", res),
+        }
+        let reserved = Account {
+            name: "admin".to_string(),
+            ..saved.clone()
+        };
+        assert!(matches!(
+            reserved.upsert(&pool, &[], "id").await,
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            reserved.update(&pool, "id", None).await,
+            Err(Error::Validation(_))
+        ));
+        let res = Account::insert_many(&pool, &[account, reserved], &[]).await;
+        assert!(matches!(res, Err(Error::Validation(_))));
+        assert_eq!(Account::count(&pool, &Filter::all()).await.unwrap(), 1);
+    }
+
+    #[derive(Debug, Clone, Serialize, crate::SqliteModel)]
+    #[model(table = "stamped")]
+    struct Stamped {
+        #[model(primary_key, skip_insert)]
+        id: i64,
+        name: String,
+        #[model(created_at)]
+        created_at: i64,
+        #[model(updated_at)]
+        updated_at: String,
+        #[model(updated_at = "unix_millis")]
+        touched_ms: i64,
+    }
+
+    #[tokio::test]
+    async fn test_timestamps() {
+        assert_eq!(
+            Stamped::columns()[2],
+            Column::new("created_at")
+                .affinity(Affinity::Integer)
+                .not_null()
+                .skip_insert()
+                .default_expr("unixepoch()")
+                .created_at(TimeFormat::UnixSeconds)
+        );
+        assert_eq!(
+            Stamped::columns()[3].timestamp,
+            Some(Timestamp::UpdatedAt(TimeFormat::Iso8601))
+        );
+        let overridden = Column::new("at")
+            .default_expr("0")
+            .updated_at(TimeFormat::UnixMillis);
+        assert_eq!(overridden.default, Some("0"));
+
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        Stamped::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        let stale = Stamped {
+            id: 1,
+            name: "first".to_string(),
+            created_at: 0,
+            updated_at: "stale".to_string(),
+            touched_ms: 0,
+        };
+        let saved = stale.insert(&pool, &[]).await.unwrap();
+        assert!(saved.created_at > 1_600_000_000);
+        assert!(saved.touched_ms > 1_600_000_000_000);
+        assert_eq!(saved.updated_at.len(), "2024-05-01T09:30:00.000Z".len());
+        assert!(saved.updated_at.ends_with('Z'));
+
+        let renamed = Stamped {
+            name: "renamed".to_string(),
+            ..stale.clone()
+        };
+        let updated = renamed.update(&pool, "id", Some(&["name"])).await.unwrap();
+        assert_eq!(updated.name, "renamed");
+        assert_eq!(updated.created_at, saved.created_at);
+        assert!(updated.updated_at >= saved.updated_at);
+        assert!(updated.touched_ms >= saved.touched_ms);
+        assert!(matches!(
+            renamed.update(&pool, "id", Some(&[])).await,
+            Err(Error::InvalidQuery(_))
+        ));
+
+        let upserted = stale.upsert(&pool, &[], "id").await.unwrap();
+        assert_eq!(upserted.name, "first");
+        assert_eq!(upserted.created_at, saved.created_at);
+        assert!(upserted.updated_at >= updated.updated_at);
+        let upserted = Stamped::upsert_many(&pool, &[renamed], &[], "id")
+            .await
+            .unwrap();
+        assert_eq!(upserted[0].created_at, saved.created_at);
+        assert_ne!(upserted[0].updated_at, "stale");
+    }
+
+    #[derive(Debug, Clone, Serialize, crate::SqliteModel)]
+    #[model(table = "notes", hooks)]
+    struct Note {
+        #[model(primary_key, skip_insert)]
+        id: i64,
+        body: String,
+        #[model(deleted_at)]
+        deleted_at: Option<String>,
+    }
+
+    static RESTORED: std::sync::atomic::AtomicI64 = std::sync::atomic::AtomicI64::new(0);
+
+    #[async_trait]
+    impl crate::ModelHooks for Note {
+        async fn before_restore(filter: &Filter) -> Result<(), Error> {
+            match filter == &Filter::eq("id", 3) {
+                true => Err(Error::InvalidQuery("note 3 stays deleted".to_string())),
+                false => Ok(()),
+            }
+        }
+
+        async fn after_restore(&self) -> Result<(), Error> {
+            RESTORED.fetch_add(self.id, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete() {
+        assert_eq!(
+            Note::columns()[2],
+            Column::new("deleted_at")
+                .affinity(Affinity::Text)
+                .deleted_at(TimeFormat::Iso8601)
+        );
+        assert_eq!(Note::columns()[2].default, None);
+
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        Note::create_table(&pool, IfNotExists(false)).await.unwrap();
+        let notes: Vec<Note> = ["a", "b", "c"]
+            .iter()
+            .map(|body| Note {
+                id: 0,
+                body: body.to_string(),
+                deleted_at: None,
+            })
+            .collect();
+        Note::insert_many(&pool, &notes, &[]).await.unwrap();
+
+        let deleted = Note::delete(&pool, "id", 1.into()).await.unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert!(deleted[0].deleted_at.is_some());
+        assert!(Note::delete(&pool, "id", 1.into())
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            Note::select_one(&pool, "id", 1.into()).await,
+            Err(Error::NotFound { .. })
+        ));
+        let options = SelectOptions::new().order_by("id", Order::Asc);
+        let ids = |notes: Vec<Note>| notes.iter().map(|n| n.id).collect::<Vec<_>>();
+        let visible = Note::select_all(&pool, &options).await.unwrap();
+        assert_eq!(ids(visible), [2, 3]);
+        assert_eq!(Note::count(&pool, &Filter::all()).await.unwrap(), 2);
+        assert!(!Note::exists(&pool, &Filter::eq("id", 1)).await.unwrap());
+        let page = Note::paginate(&pool, None, Order::Asc, 1, None)
+            .await
+            .unwrap();
+        assert_eq!(ids(page.items), [2]);
+        let cursor = page.next_cursor.unwrap();
+        let page = Note::paginate(&pool, None, Order::Asc, 1, Some(&cursor))
+            .await
+            .unwrap();
+        assert_eq!(ids(page.items), [3]);
+        assert!(!page.has_more);
+
+        let all = Note::with_trashed(&pool, &Filter::all(), &options).await;
+        assert_eq!(ids(all.unwrap()), [1, 2, 3]);
+        let trashed = Note::only_trashed(&pool, &Filter::all(), &options).await;
+        assert_eq!(ids(trashed.unwrap()), [1]);
+
+        let edited = Note {
+            id: 1,
+            body: "edited".to_string(),
+            deleted_at: None,
+        };
+        assert!(matches!(
+            edited.update(&pool, "id", None).await,
+            Err(Error::NotFound { .. })
+        ));
+        let upserted = edited.upsert(&pool, &[], "id").await.unwrap();
+        assert_eq!(upserted.body, "edited");
+        assert_eq!(upserted.deleted_at, None);
+        assert_eq!(Note::count(&pool, &Filter::all()).await.unwrap(), 3);
+        assert_eq!(RESTORED.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        Note::delete_where(&pool, &Filter::is_in("id", [1, 2]))
+            .await
+            .unwrap();
+        let edited = Note { id: 2, ..edited };
+        let upserted = Note::upsert_many(&pool, &[edited], &[], "id")
+            .await
+            .unwrap();
+        assert_eq!(upserted[0].deleted_at, None);
+        assert_eq!(Note::count(&pool, &Filter::all()).await.unwrap(), 2);
+
+        let restored = Note::restore(&pool, &Filter::all()).await.unwrap();
+        assert_eq!(ids(restored.clone()), [1]);
+        assert_eq!(restored[0].deleted_at, None);
+        assert_eq!(RESTORED.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(Note::restore(&pool, &Filter::all())
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(Note::count(&pool, &Filter::all()).await.unwrap(), 3);
+
+        Note::delete(&pool, "id", 3.into()).await.unwrap();
+        let res = Note::restore(&pool, &Filter::eq("id", 3)).await;
+        assert!(matches!(res, Err(Error::InvalidQuery(_))));
+        assert_eq!(Note::count(&pool, &Filter::all()).await.unwrap(), 2);
+
+        Note::delete(&pool, "id", 2.into()).await.unwrap();
+        let removed = Note::force_delete(&pool, &Filter::all()).await.unwrap();
+        assert_eq!(removed.len(), 3);
+        let all = Note::with_trashed(&pool, &Filter::all(), &options).await;
+        assert!(all.unwrap().is_empty());
+
+        assert!(matches!(
+            TestModel::only_trashed(&pool, &Filter::all(), &options).await,
+            Err(Error::InvalidQuery(_))
+        ));
+        assert!(matches!(
+            TestModel::restore(&pool, &Filter::all()).await,
+            Err(Error::InvalidQuery(_))
+        ));
+    }
+
+    #[derive(Debug, Clone, Serialize, crate::SqliteModel)]
+    #[model(table = "documents")]
+    struct Document {
+        #[model(primary_key)]
+        id: i64,
+        title: String,
+        #[model(version)]
+        version: i64,
+    }
+
+    #[tokio::test]
+    async fn test_optimistic_locking() {
+        assert_eq!(Document::columns()[2].default, Some("0"));
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        Document::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+        let doc = Document {
+            id: 1,
+            title: "draft".to_string(),
+            version: 7,
+        };
+        let saved = doc.insert(&pool, &[]).await.unwrap();
+        assert_eq!(saved.version, 0);
+
+        let edited = Document {
+            title: "edited".to_string(),
+            ..saved.clone()
+        };
+        let edited = edited.update(&pool, "id", None).await.unwrap();
+        assert_eq!(edited.version, 1);
+        let stale = Document {
+            title: "stale".to_string(),
+            ..saved.clone()
+        };
+        assert!(matches!(
+            stale.update(&pool, "id", Some(&["title"])).await,
+            Err(Error::Conflict { column, .. }) if column == "version"
+        ));
+        assert!(matches!(
+            stale.upsert(&pool, &[], "id").await,
+            Err(Error::Conflict { .. })
+        ));
+        let missing = Document {
+            id: 9,
+            ..edited.clone()
+        };
+        assert!(matches!(
+            missing.update(&pool, "id", None).await,
+            Err(Error::Conflict { .. })
+        ));
+        let current = Document::select_one(&pool, "id", 1.into()).await.unwrap();
+        assert_eq!((current.title.as_str(), current.version), ("edited", 1));
+
+        let upserted = edited.upsert(&pool, &[], "id").await.unwrap();
+        assert_eq!(upserted.version, 2);
+        let second = Document {
+            id: 2,
+            title: "second".to_string(),
+            version: 7,
+        };
+        let second = second.upsert(&pool, &[], "id").await.unwrap();
+        assert_eq!(second.version, 0);
+
+        let stale = Document {
+            version: 5,
+            ..second.clone()
+        };
+        let res = Document::upsert_many(&pool, &[upserted.clone(), stale], &[], "id").await;
+        assert!(matches!(res, Err(Error::Conflict { .. })));
+        let current = Document::select_one(&pool, "id", 1.into()).await.unwrap();
+        assert_eq!(current.version, 2);
+        let third = Document {
+            id: 3,
+            title: "third".to_string(),
+            version: 4,
+        };
+        let saved = Document::upsert_many(&pool, &[upserted, second, third], &[], "id")
+            .await
+            .unwrap();
+        let versions: Vec<i64> = saved.iter().map(|d| d.version).collect();
+        assert_eq!(versions, [3, 1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        create_table(&pool).await.unwrap();
+        DerivedModel::create_table(&pool, IfNotExists(false))
+            .await
+            .unwrap();
+
+        match TestModel::select_one(&pool, "id", 1.into()).await {
+            Err(Error::NotFound { table, column }) => {
+                assert_eq!(table, "TestModel");
+                assert_eq!(column, "id");
+            }
+            res => panic!("expected not found, found {:?}", res),
+        }
+
+        let res = TestModel::select_many(&pool, "id", u64::MAX.into()).await;
+        match res {
+            Err(Error::UnsupportedValue { column, value }) => {
+                assert_eq!(column, "id");
+                assert_eq!(value, serde_json::json!(u64::MAX));
+            }
+            res => panic!("expected an unsupported value, found {:?}", res),
+        }
+
+        let user = DerivedModel {
+            id: 0,
+            name: "alice".to_string(),
+            email: None,
+        };
+        user.insert(&pool, &[]).await.unwrap();
+        match user.insert(&pool, &[]).await {
+            Err(Error::ConstraintViolation { kind, constraint }) => {
+                assert_eq!(kind, ConstraintKind::Unique);
+                assert_eq!(constraint, "users.user_name");
+            }
+            res => panic!("expected a constraint violation, found {:?}", res),
+        }
+    }
 }
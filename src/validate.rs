@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{Column, Error};
+
+/// A check applied to the value of one column before it is written. Null values pass every
+/// rule except `NonEmpty`, so optional columns are only checked when they hold a value
+#[derive(Debug, Clone, Copy)]
+pub enum Rule {
+    /// The number of characters of a string, or elements of an array, is within the bounds
+    Length {
+        min: Option<usize>,
+        max: Option<usize>,
+    },
+    /// A number is within the bounds
+    Range { min: Option<f64>, max: Option<f64> },
+    /// A string matches the regular expression
+    Regex(&'static str),
+    /// A string looks like an email address
+    Email,
+    /// The value is not null, an empty or blank string, or an empty array
+    NonEmpty,
+    /// A function which returns a message describing why the value is invalid
+    Custom(fn(&Value) -> Result<(), String>),
+}
+
+/// A [`Rule`] applied to the column `column`
+#[derive(Debug, Clone, Copy)]
+pub struct FieldRule {
+    pub column: &'static str,
+    pub rule: Rule,
+}
+
+impl FieldRule {
+    pub const fn new(column: &'static str, rule: Rule) -> Self {
+        FieldRule { column, rule }
+    }
+}
+
+/// A value which broke a [`Rule`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    /// The name of the struct field, which is the column name if the columns are not declared
+    pub field: String,
+    /// The rule which was broken, eg `length` or `email`
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Rule {
+    fn code(&self) -> &'static str {
+        match self {
+            Rule::Length { .. } => "length",
+            Rule::Range { .. } => "range",
+            Rule::Regex(_) => "regex",
+            Rule::Email => "email",
+            Rule::NonEmpty => "non_empty",
+            Rule::Custom(_) => "custom",
+        }
+    }
+
+    /// Check `val` against the rule, returning a message describing the problem if it fails
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidQuery` if the pattern of a `Regex` rule is not a valid regular
+    /// expression
+    fn check(&self, val: &Value) -> Result<Option<String>, Error> {
+        if val.is_null() && !matches!(self, Rule::NonEmpty) {
+            return Ok(None);
+        }
+        let message = match self {
+            Rule::Length { min, max } => {
+                let len = match val {
+                    Value::String(s) => s.chars().count(),
+                    Value::Array(a) => a.len(),
+                    _ => return Ok(Some("must be a string or an array".to_string())),
+                };
+                match (min, max) {
+                    (Some(min), _) if len < *min => Some(format!("must be at least {} long", min)),
+                    (_, Some(max)) if len > *max => Some(format!("must be at most {} long", max)),
+                    _ => None,
+                }
+            }
+            Rule::Range { min, max } => match val.as_f64() {
+                Some(n) => match (min, max) {
+                    (Some(min), _) if n < *min => Some(format!("must be at least {}", min)),
+                    (_, Some(max)) if n > *max => Some(format!("must be at most {}", max)),
+                    _ => None,
+                },
+                None => Some("must be a number".to_string()),
+            },
+            Rule::Regex(pattern) => match val.as_str() {
+                Some(s) if !is_match(pattern, s)? => Some(format!("must match {}", pattern)),
+                Some(_) => None,
+                None => Some("must be a string".to_string()),
+            },
+            Rule::Email => match val.as_str() {
+                Some(s) if is_email(s) => None,
+                _ => Some("must be an email address".to_string()),
+            },
+            Rule::NonEmpty => match val {
+                Value::Null => Some("must not be null".to_string()),
+                Value::String(s) if s.trim().is_empty() => Some("must not be empty".to_string()),
+                Value::Array(a) if a.is_empty() => Some("must not be empty".to_string()),
+                _ => None,
+            },
+            Rule::Custom(check) => check(val).err(),
+        };
+        Ok(message)
+    }
+}
+
+/// Match `s` against `pattern`, compiling each pattern only once
+fn is_match(pattern: &'static str, s: &str) -> Result<bool, Error> {
+    static CACHE: OnceLock<Mutex<HashMap<&'static str, Regex>>> = OnceLock::new();
+    let mut cache = CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if !cache.contains_key(pattern) {
+        let regex = Regex::new(pattern)
+            .map_err(|e| Error::InvalidQuery(format!("invalid validation pattern: {}", e)))?;
+        cache.insert(pattern, regex);
+    }
+    Ok(cache[pattern].is_match(s))
+}
+
+/// A deliberately loose check: one `@` between a local part and a domain containing a dot,
+/// without whitespace
+fn is_email(s: &str) -> bool {
+    match s.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.split('.').count() > 1
+                && domain.split('.').all(|part| !part.is_empty())
+                && !s.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+/// Check every rule in `rules` against the values of `col_vals`
+///
+/// # Errors
+/// Returns `Error::Validation` with every broken rule if any fails, and `Error::UnknownColumn`
+/// if a rule names a column which the model does not have
+pub(crate) fn validate_values(
+    columns: &[Column],
+    rules: &[FieldRule],
+    col_vals: &[(String, Value)],
+) -> Result<(), Error> {
+    let mut errors = Vec::new();
+    for rule in rules {
+        let val = col_vals.iter().find(|(col, _)| col == rule.column);
+        let val = val.ok_or(Error::UnknownColumn(rule.column.to_string()))?;
+        if let Some(message) = rule.rule.check(&val.1)? {
+            let field = columns.iter().find(|c| c.name == rule.column);
+            errors.push(FieldError {
+                field: field.map(|c| c.field).unwrap_or(rule.column).to_string(),
+                code: rule.rule.code(),
+                message,
+            });
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(Error::Validation(errors)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{is_email, validate_values, FieldRule, Rule};
+    use crate::{Column, Error};
+
+    fn no_admin(val: &Value) -> Result<(), String> {
+        match val.as_str() {
+            Some("admin") => Err("is reserved".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        let cases = [
+            (
+                Rule::Length {
+                    min: Some(2),
+                    max: Some(3),
+                },
+                json!("ab"),
+                true,
+            ),
+            (
+                Rule::Length {
+                    min: Some(2),
+                    max: Some(3),
+                },
+                json!("é"),
+                false,
+            ),
+            (
+                Rule::Length {
+                    min: None,
+                    max: Some(1),
+                },
+                json!([1, 2]),
+                false,
+            ),
+            (
+                Rule::Range {
+                    min: Some(0.0),
+                    max: Some(1.5),
+                },
+                json!(1.5),
+                true,
+            ),
+            (
+                Rule::Range {
+                    min: Some(0.0),
+                    max: None,
+                },
+                json!(-1),
+                false,
+            ),
+            (
+                Rule::Range {
+                    min: Some(0.0),
+                    max: None,
+                },
+                json!("1"),
+                false,
+            ),
+            (Rule::Regex("^[a-z]+$"), json!("abc"), true),
+            (Rule::Regex("^[a-z]+$"), json!("abc1"), false),
+            (Rule::Email, json!("a.b@example.com"), true),
+            (Rule::Email, json!("a b@example.com"), false),
+            (Rule::NonEmpty, json!(" "), false),
+            (Rule::NonEmpty, json!(null), false),
+            (Rule::NonEmpty, json!(0), true),
+            (Rule::Email, json!(null), true),
+            (Rule::Custom(no_admin), json!("admin"), false),
+            (Rule::Custom(no_admin), json!("alice"), true),
+        ];
+        for (rule, val, valid) in cases {
+            let res = rule.check(&val).unwrap();
+            assert_eq!(res.is_none(), valid, "{:?} {}", rule, val);
+        }
+        assert!(Rule::Regex("(").check(&json!("a")).is_err());
+
+        assert!(is_email("alice@mail.example.com"));
+        assert!(!is_email("alice@example"));
+        assert!(!is_email("@example.com"));
+        assert!(!is_email("alice@@example.com"));
+        assert!(!is_email("alice@example..com"));
+    }
+
+    #[test]
+    fn test_validate_values() {
+        let columns = [Column::new("user_name").field("name"), Column::new("age")];
+        let rules = [
+            FieldRule::new("user_name", Rule::NonEmpty),
+            FieldRule::new("user_name", Rule::Custom(no_admin)),
+            FieldRule::new(
+                "age",
+                Rule::Range {
+                    min: Some(0.0),
+                    max: Some(150.0),
+                },
+            ),
+        ];
+        let valid = [
+            ("user_name".to_string(), json!("alice")),
+            ("age".to_string(), json!(30)),
+        ];
+        assert!(validate_values(&columns, &rules, &valid).is_ok());
+
+        let invalid = [
+            ("user_name".to_string(), json!("admin")),
+            ("age".to_string(), json!(200)),
+        ];
+        match validate_values(&columns, &rules, &invalid) {
+            Err(Error::Validation(errors)) => {
+                let codes: Vec<(&str, &str)> =
+                    errors.iter().map(|e| (e.field.as_str(), e.code)).collect();
+                assert_eq!(codes, [("name", "custom"), ("age", "range")]);
+                assert_eq!(errors[0].message, "is reserved");
+            }
+            res => panic!("expected a validation error, found {:?}", res),
+        }
+
+        let rules = [FieldRule::new("email", Rule::Email)];
+        assert!(matches!(
+            validate_values(&columns, &rules, &valid),
+            Err(Error::UnknownColumn(_))
+        ));
+    }
+}